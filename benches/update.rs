@@ -0,0 +1,64 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use diffuser::config::SimulationConfig;
+use diffuser::model::Model;
+use diffuser::patterns::Pattern;
+use diffuser::rect::Rect;
+
+const SIDES: [usize; 3] = [256, 512, 1024];
+const FRAME_TIME: f32 = 1.0 / 60.0;
+
+/// A canvas of `side` by `side` cells that starts out the same on every run
+fn seeded_model(side: usize) -> Model {
+    let config = SimulationConfig {
+        rng_seed: Some(0),
+        ..SimulationConfig::with_resolution(Rect::new(side, side))
+    };
+
+    Model::new_seeded(&config, Pattern::Random)
+}
+
+/// Like `seeded_model`, but it never settles. A settled canvas skips diffusion entirely, which isn't what's being
+/// measured.
+fn unsettling_model(side: usize) -> Model {
+    let mut model = seeded_model(side);
+    model.settle_epsilon = 0.0;
+    model
+}
+
+fn bench_update(c: &mut Criterion) {
+    let mut group = c.benchmark_group("update");
+    for side in SIDES.iter() {
+        group.bench_with_input(BenchmarkId::from_parameter(side), side, |b, &side| {
+            let mut model = unsettling_model(side);
+            b.iter(|| model.update(FRAME_TIME));
+        });
+    }
+    group.finish();
+}
+
+fn bench_update_with_noise(c: &mut Criterion) {
+    let mut group = c.benchmark_group("update_with_noise");
+    for side in SIDES.iter() {
+        group.bench_with_input(BenchmarkId::from_parameter(side), side, |b, &side| {
+            let mut model = unsettling_model(side);
+            model.noise_rate = 0.01;
+            b.iter(|| model.update(FRAME_TIME));
+        });
+    }
+    group.finish();
+}
+
+fn bench_draw(c: &mut Criterion) {
+    let mut group = c.benchmark_group("draw");
+    for side in SIDES.iter() {
+        group.bench_with_input(BenchmarkId::from_parameter(side), side, |b, &side| {
+            let model = seeded_model(side);
+            let mut frame = vec![0; side * side * 4];
+            b.iter(|| model.draw(&mut frame).unwrap());
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_update, bench_update_with_noise, bench_draw);
+criterion_main!(benches);
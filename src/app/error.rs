@@ -8,4 +8,76 @@ pub enum Error {
         index: usize,
         len: usize,
     },
+    #[error("No cell at {{x: {x}, y: {y}}}, matrix is {width} cells wide and {height} cells high")]
+    InvalidXyIndex {
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    },
+    #[error("Can't save an empty canvas")]
+    EmptyCanvas,
+    #[error(transparent)]
+    Image(#[from] image::ImageError),
+    #[error("Can't save a recording with no frames in it")]
+    EmptyRecording,
+    #[error("Can't record frames of {width}x{height}, GIFs can be at most 65535 pixels on a side")]
+    RecordingTooLarge { width: u32, height: u32 },
+    #[error(transparent)]
+    Gif(#[from] gif::EncodingError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("Unknown argument \"{argument}\"")]
+    UnknownArgument { argument: String },
+    #[error("Missing a value for {flag}")]
+    MissingArgumentValue { flag: String },
+    #[error("\"{value}\" isn't a valid value for {flag}")]
+    InvalidArgumentValue { flag: String, value: String },
+    #[error(
+        "Unknown pattern \"{name}\", expected one of random, checkerboard, gradient, circle, or drop"
+    )]
+    UnknownPattern { name: String },
+    #[error("Unknown demo \"{name}\", expected drop")]
+    UnknownDemo { name: String },
+    #[error("Unknown direction \"{name}\", expected a compass direction like N, NE, or northeast")]
+    UnknownDirection { name: String },
+    #[error("A {width}x{height} matrix needs {} cells but {len} were given", width * height)]
+    InvalidMatrixLength {
+        len: usize,
+        width: usize,
+        height: usize,
+    },
+    #[error("Line {line} of the input recording, \"{text}\", isn't an event")]
+    InvalidReplay { line: usize, text: String },
+    #[error(
+        "The input recording was made at {recorded} updates a second, replay it with --updates-per-second {recorded} instead of {given}"
+    )]
+    ReplayRateMismatch { recorded: u32, given: u32 },
+    #[error("A {width}x{height} RGBA frame needs {} bytes but {len} were given", 4 * width * height)]
+    InvalidFrameLength {
+        len: usize,
+        width: usize,
+        height: usize,
+    },
+    #[error(
+        "Can't load a {width}x{height} canvas into a {expected_width}x{expected_height} window"
+    )]
+    CanvasSizeMismatch {
+        width: usize,
+        height: usize,
+        expected_width: usize,
+        expected_height: usize,
+    },
+    #[error(
+        "The decay map holds {value} at {{x: {x}, y: {y}}}, decay multipliers can't be negative or non-finite"
+    )]
+    InvalidDecayMultiplier { x: usize, y: usize, value: f32 },
+    #[error("Can't draw {matrix_len} cells into a frame of {frame_pixels} pixels")]
+    SizeMismatch {
+        frame_pixels: usize,
+        matrix_len: usize,
+    },
+    #[cfg(feature = "serde")]
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
 }
@@ -1,35 +1,59 @@
 mod error;
 
 use crate::{
-    constants::{DEFAULT_RESOLUTION_H, DEFAULT_RESOLUTION_W},
-    model::Model,
-    rect::Rect,
+    cli::Args,
+    constants::{
+        DECAY_FACTOR_STEP, DEFAULT_NOISE_RATE, FPS_UPDATE_INTERVAL, GAMMA_STEP, MAX_GAMMA,
+        MAX_UPDATES_PER_FRAME, MIN_GAMMA, RECORDER_FPS,
+    },
+    model::{ColorMode, DecayMode, DiffusionKernel, DiffusionMode, Model, NeighbourhoodKind},
+    recorder::Recorder,
+    replay::{InputEvent, InputRecorder, Replay},
+    vector2::Vector2,
 };
 use circular_queue::CircularQueue;
 pub use error::Error;
 use log::{debug, error, info};
-use pixels::{Pixels, SurfaceTexture};
-use std::time::Instant;
-use winit::{dpi::LogicalSize, event::VirtualKeyCode, event_loop::ControlFlow, window::Window};
+use pixels::{Pixels, PixelsBuilder, SurfaceTexture};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use winit::{
+    dpi::{LogicalSize, PhysicalSize},
+    event::VirtualKeyCode,
+    event_loop::ControlFlow,
+    window::{Fullscreen, Window},
+};
 use winit::{event::Event, event_loop::EventLoop, window::WindowBuilder};
 use winit_input_helper::WinitInputHelper;
 
 pub struct App {
     pub event_loop: EventLoop<()>,
     pub input: WinitInputHelper,
+    /// Sleep at the end of each frame so the app runs no faster than this, or as fast as it can when it's `None`
+    pub max_fps: Option<u32>,
     pub model: Model,
     pub pixels: Pixels<Window>,
+    /// Where to save a recording of the input when the app quits
+    pub record_input: Option<PathBuf>,
+    /// A recording of input being played back into the model. The mouse and the keys it records are ignored until it's
+    /// finished.
+    pub replay: Option<Replay>,
+    /// The window title that the FPS readout is appended to
+    pub title: String,
+    /// How many times a second the simulation is updated, however fast frames are drawn
+    pub updates_per_second: u32,
     pub window: Window,
 }
 
 impl App {
-    pub fn new() -> App {
+    pub fn new(args: &Args) -> App {
         let event_loop = EventLoop::new();
         let input = WinitInputHelper::new();
+        let (width, height) = args.resolution();
         let window = {
-            let size = LogicalSize::new(DEFAULT_RESOLUTION_W as f64, DEFAULT_RESOLUTION_H as f64);
+            let size = LogicalSize::new(width as f64, height as f64);
             WindowBuilder::new()
-                .with_title("Diffuser")
+                .with_title(args.title())
                 .with_inner_size(size)
                 .with_min_inner_size(size)
                 .build(&event_loop)
@@ -40,13 +64,57 @@ impl App {
             let window_size = window.inner_size();
             let surface_texture =
                 SurfaceTexture::new(window_size.width, window_size.height, &window);
-            Pixels::new(DEFAULT_RESOLUTION_W, DEFAULT_RESOLUTION_H, surface_texture).unwrap()
+            let (frame_width, frame_height) = args.frame_size();
+            PixelsBuilder::new(frame_width, frame_height, surface_texture)
+                .present_mode(args.present_mode())
+                .build()
+                .unwrap()
         };
 
-        let model = Model::new(Rect::new(
-            window.inner_size().height as usize,
-            window.inner_size().width as usize,
-        ));
+        // The canvas must match the pixel buffer rather than the window, which may differ on high-DPI displays
+        let mut model = Model::new(&args.config());
+        model.sim_scale = args.sim_scale() as usize;
+
+        if let Some(path) = &args.image {
+            match model.load_image(path) {
+                Ok(()) => info!("Seeded canvas from {}", path.display()),
+                Err(e) => error!("Failed to seed canvas from {}: {}", path.display(), e),
+            }
+        }
+
+        if let Some(path) = &args.background {
+            match model.load_background(path) {
+                Ok(()) => info!("Loaded background from {}", path.display()),
+                Err(e) => error!("Failed to load background from {}: {}", path.display(), e),
+            }
+        }
+
+        if let Some(pattern) = args.seed {
+            model.seed_pattern(pattern);
+            info!("Seeded canvas with {:?} pattern", pattern);
+        }
+
+        if let Some(demo) = args.demo {
+            model.seed_pattern(demo.pattern());
+            info!("Running the {:?} demo", demo);
+        }
+
+        let replay = args.replay.as_deref().and_then(|path| {
+            let replay = Replay::load(path).and_then(|replay| {
+                replay.check_updates_per_second(args.updates_per_second())?;
+                Ok(replay)
+            });
+            match replay {
+                Ok(replay) => {
+                    info!("Replaying input from {}", path.display());
+                    Some(replay)
+                }
+                Err(e) => {
+                    error!("Failed to load input from {}: {}", path.display(), e);
+                    None
+                }
+            }
+        });
 
         Self {
             event_loop,
@@ -54,30 +122,79 @@ impl App {
             pixels,
             model,
             input,
+            max_fps: args.max_fps,
+            record_input: args.record_input.clone(),
+            replay,
+            title: args.title().to_owned(),
+            updates_per_second: args.updates_per_second(),
         }
     }
 }
 
+impl Default for App {
+    fn default() -> Self {
+        Self::new(&Args::default())
+    }
+}
+
 pub fn run(app: App) {
     let App {
         event_loop,
         mut input,
+        max_fps,
         mut model,
         mut pixels,
+        record_input,
+        mut replay,
+        title,
+        updates_per_second,
         window,
     } = app;
 
-    let mut frame_time = 0.16;
+    let mut frame_time = Duration::default();
+    let mut timestep = FixedTimestep::new(updates_per_second);
     let mut time_of_last_frame_start = Instant::now();
 
     let mut frame_counter = 0;
     let mut fps_values = CircularQueue::with_capacity(5);
     let mut time_of_last_fps_counter_update = Instant::now();
 
+    let mut previous_buffer_xy: Option<Vector2<f32>> = None;
+    // What to go back to when leaving fullscreen
+    let mut windowed_size = window.inner_size();
+
+    let mut recorder: Option<Recorder> = None;
+    let mut time_of_last_recorded_frame = Instant::now();
+
+    // How many times the model has been updated, which is what recorded input is timed by
+    let mut tick = 0;
+    let mut input_recorder = record_input
+        .as_ref()
+        .map(|_| InputRecorder::new(updates_per_second));
+
     event_loop.run(move |event, _, control_flow| {
         // Draw the current frame
         if let Event::RedrawRequested(_) = event {
-            model.draw(pixels.get_frame());
+            if let Err(e) = model.draw(pixels.get_frame()) {
+                error!("model.draw() failed: {}", e);
+                *control_flow = ControlFlow::Exit;
+                return;
+            }
+
+            if let Some(recorder) = recorder.as_mut() {
+                if time_of_last_recorded_frame.elapsed().as_secs_f32() >= 1.0 / RECORDER_FPS {
+                    time_of_last_recorded_frame = Instant::now();
+                    recorder.capture(pixels.get_frame());
+                }
+            }
+
+            // Drawn after the recorder has its frame, so recordings don't include it
+            if model.show_hud {
+                if let Err(e) = model.draw_hud(pixels.get_frame()) {
+                    error!("model.draw_hud() failed: {}", e);
+                }
+            }
+
             if pixels
                 .render()
                 .map_err(|e| error!("pixels.render() failed: {}", e))
@@ -92,54 +209,644 @@ pub fn run(app: App) {
         if input.update(&event) {
             // Close events
             if input.key_pressed(VirtualKeyCode::Escape) || input.quit() {
+                if let (Some(input_recorder), Some(path)) = (&input_recorder, &record_input) {
+                    match input_recorder.save(path) {
+                        Ok(()) => info!("Saved recorded input to {}", path.display()),
+                        Err(e) => {
+                            error!("Failed to save recorded input to {}: {}", path.display(), e)
+                        }
+                    }
+                }
+
                 *control_flow = ControlFlow::Exit;
                 return;
             }
 
-            if input.mouse_pressed(0) {
-                debug!("Pressed LMB");
-                model.left_click_is_held_down = true
-            } else if input.mouse_released(0) {
-                debug!("Released LMB");
-                model.left_click_is_held_down = false
+            // Only the surface is resized, the canvas keeps its size and is scaled to fit the screen
+            if input.key_pressed(VirtualKeyCode::F11) {
+                let fullscreen = window.fullscreen().is_none();
+                if fullscreen {
+                    windowed_size = window.inner_size();
+                    window.set_fullscreen(Some(Fullscreen::Borderless(None)));
+                } else {
+                    window.set_fullscreen(None);
+                }
+
+                let monitor_size = window.current_monitor().map(|monitor| monitor.size());
+                let size = surface_size(fullscreen, monitor_size, windowed_size);
+                pixels.resize(size.width, size.height);
+                info!("{} fullscreen", if fullscreen { "Entered" } else { "Left" });
+            }
+
+            // While a replay is playing, the mouse and the keys it records are left to it, so what's recorded isn't
+            // overwritten by whatever the user happens to do
+            let replaying = replay.as_ref().is_some_and(|replay| !replay.is_finished());
+
+            if !replaying {
+                let fill_requested = input.mouse_pressed(0) && input.held_shift();
+                model.freezing = input.held_alt();
+
+                if (input.mouse_pressed(0) && !fill_requested) || input.mouse_pressed(1) {
+                    model.begin_stroke();
+                }
+
+                if fill_requested {
+                    debug!("Pressed Shift+LMB");
+                    let Vector2 { x, y } = model.mouse_xy;
+                    if x >= 0.0 && y >= 0.0 {
+                        let (x, y) = (x.round() as usize, y.round() as usize);
+                        let value = model.paint_value;
+                        perform(
+                            &mut model,
+                            input_recorder.as_mut(),
+                            tick,
+                            InputEvent::Fill { x, y, value },
+                        );
+                    }
+                } else if input.mouse_pressed(0) {
+                    debug!("Pressed LMB");
+                    model.left_click_is_held_down = true
+                } else if input.mouse_released(0) {
+                    debug!("Released LMB");
+                    model.left_click_is_held_down = false
+                }
+
+                if input.mouse_pressed(1) {
+                    debug!("Pressed RMB");
+                    model.right_click_is_held_down = true
+                } else if input.mouse_released(1) {
+                    debug!("Released RMB");
+                    model.right_click_is_held_down = false
+                }
+
+                if (input.mouse_released(0) || input.mouse_released(1))
+                    && !model.left_click_is_held_down
+                    && !model.right_click_is_held_down
+                {
+                    model.end_stroke();
+                }
+            }
+
+            if !replaying && input.held_control() && input.key_pressed(VirtualKeyCode::Z) {
+                if model.undo() {
+                    record(&model, input_recorder.as_mut(), tick, InputEvent::Undo);
+                } else {
+                    info!("Nothing to undo");
+                }
+            }
+
+            if !replaying && input.held_control() && input.key_pressed(VirtualKeyCode::Y) {
+                if model.redo() {
+                    record(&model, input_recorder.as_mut(), tick, InputEvent::Redo);
+                } else {
+                    info!("Nothing to redo");
+                }
+            }
+
+            if !replaying && input.key_pressed(VirtualKeyCode::RBracket) {
+                model.brush_radius += 1;
+                info!("Brush radius {}", model.brush_radius);
+            } else if !replaying && input.key_pressed(VirtualKeyCode::LBracket) {
+                model.brush_radius = model.brush_radius.saturating_sub(1).max(1);
+                info!("Brush radius {}", model.brush_radius);
+            }
+
+            if !replaying && input.key_pressed(VirtualKeyCode::Equals) {
+                model.config.decay_factor = model
+                    .decay_mode
+                    .cap_rate(model.config.decay_factor + DECAY_FACTOR_STEP);
+                info!("Decay factor {}", model.config.decay_factor);
+            } else if !replaying && input.key_pressed(VirtualKeyCode::Minus) {
+                model.config.decay_factor =
+                    (model.config.decay_factor - DECAY_FACTOR_STEP).max(0.0);
+                info!("Decay factor {}", model.config.decay_factor);
+            }
+
+            if input.key_pressed(VirtualKeyCode::Apostrophe) {
+                model.gamma = (model.gamma + GAMMA_STEP).clamp(MIN_GAMMA, MAX_GAMMA);
+                info!("Gamma {}", model.gamma);
+            } else if input.key_pressed(VirtualKeyCode::Semicolon) {
+                model.gamma = (model.gamma - GAMMA_STEP).clamp(MIN_GAMMA, MAX_GAMMA);
+                info!("Gamma {}", model.gamma);
+            }
+
+            if !replaying && input.key_pressed(VirtualKeyCode::M) {
+                model.color_mode = match model.color_mode {
+                    ColorMode::Grayscale => ColorMode::Rgb,
+                    ColorMode::Rgb => ColorMode::Grayscale,
+                };
+                info!("Color mode {:?}", model.color_mode);
+            }
+
+            if !replaying && input.key_pressed(VirtualKeyCode::K) {
+                model.cycle_paint_color();
+                info!("Paint color {:?}", model.paint_color);
+            }
+
+            for digit in DIGIT_KEYS.iter() {
+                if replaying || !input.key_pressed(*digit) {
+                    continue;
+                }
+
+                if let Some(value) = preset_paint_value(*digit, model.config.max_value) {
+                    model.paint_value = value;
+                    info!("Paint value {}", model.paint_value);
+                }
+            }
+
+            if !replaying && input.key_pressed(VirtualKeyCode::Q) {
+                let Vector2 { x, y } = model.mouse_xy;
+                if x >= 0.0 && y >= 0.0 {
+                    let (x, y) = (x.round() as usize, y.round() as usize);
+                    // Each injection is undone on its own, just like a stroke
+                    let value = model.paint_value;
+                    perform(
+                        &mut model,
+                        input_recorder.as_mut(),
+                        tick,
+                        InputEvent::Inject { x, y, value },
+                    );
+                    info!("Injected {} at {{x: {}, y: {}}}", value, x, y);
+                }
+            }
+
+            if !replaying && input.key_pressed(VirtualKeyCode::I) {
+                let Vector2 { x, y } = model.mouse_xy;
+                let sampled = if x >= 0.0 && y >= 0.0 {
+                    model.sample(x.round() as usize, y.round() as usize)
+                } else {
+                    None
+                };
+
+                match sampled {
+                    Some(value) => {
+                        model.paint_value = value;
+                        info!("Paint value {}", model.paint_value);
+                    }
+                    None => info!("Nothing to sample outside of the canvas"),
+                }
+            }
+
+            if input.key_pressed(VirtualKeyCode::P) {
+                model.palette = model.palette.next();
+                info!("Palette {:?}", model.palette);
+            }
+
+            if input.key_pressed(VirtualKeyCode::T) {
+                model.render_mode = model.render_mode.next();
+                info!("Render mode {:?}", model.render_mode);
+            }
+
+            if !replaying && input.key_pressed(VirtualKeyCode::B) {
+                model.diffusion_kernel = match model.diffusion_kernel {
+                    DiffusionKernel::Uniform => DiffusionKernel::Gaussian,
+                    DiffusionKernel::Gaussian => DiffusionKernel::Uniform,
+                };
+                info!("Diffusion kernel {:?}", model.diffusion_kernel);
+            }
+
+            if !replaying && input.key_pressed(VirtualKeyCode::U) {
+                model.diffusion_mode = match model.diffusion_mode {
+                    DiffusionMode::Diffuse => DiffusionMode::AntiDiffuse,
+                    DiffusionMode::AntiDiffuse => DiffusionMode::Diffuse,
+                };
+                info!("Diffusion mode {:?}", model.diffusion_mode);
+            }
+
+            if !replaying && input.key_pressed(VirtualKeyCode::L) {
+                model.decay_mode = match model.decay_mode {
+                    DecayMode::Linear => DecayMode::Exponential,
+                    DecayMode::Exponential => DecayMode::Linear,
+                };
+                model.config.decay_factor = model.decay_mode.cap_rate(model.config.decay_factor);
+                info!("Decay mode {:?}", model.decay_mode);
+            }
+
+            if !replaying && input.key_pressed(VirtualKeyCode::N) {
+                model.neighbourhood = match model.neighbourhood {
+                    NeighbourhoodKind::Moore => NeighbourhoodKind::VonNeumann,
+                    NeighbourhoodKind::VonNeumann => NeighbourhoodKind::Moore,
+                };
+                info!("Neighbourhood {:?}", model.neighbourhood);
+            }
+
+            if !replaying && input.key_pressed(VirtualKeyCode::O) {
+                model.symmetry = model.symmetry.next();
+                info!("Symmetry {:?}", model.symmetry);
+            }
+
+            if !replaying && input.key_pressed(VirtualKeyCode::J) {
+                model.noise_rate = if model.noise_rate > 0.0 {
+                    0.0
+                } else {
+                    DEFAULT_NOISE_RATE
+                };
+                info!("Noise rate {}", model.noise_rate);
             }
 
-            if input.mouse_pressed(1) {
-                debug!("Pressed RMB");
-                model.right_click_is_held_down = true
-            } else if input.mouse_released(1) {
-                debug!("Released RMB");
-                model.right_click_is_held_down = false
+            if !replaying && input.key_pressed(VirtualKeyCode::C) {
+                // Clearing is undone in one step, just like a stroke
+                perform(&mut model, input_recorder.as_mut(), tick, InputEvent::Clear);
+                info!("Cleared canvas");
             }
 
-            if let Some((x, y)) = input.mouse() {
-                model.mouse_xy.x = x;
-                model.mouse_xy.y = y;
+            if input.key_pressed(VirtualKeyCode::V) {
+                model.invert_display = !model.invert_display;
+                info!("Inverted display {}", model.invert_display);
             }
 
-            // Resize the window
+            if !replaying && input.key_pressed(VirtualKeyCode::X) {
+                // Inverting is undone in one step, just like a stroke
+                perform(
+                    &mut model,
+                    input_recorder.as_mut(),
+                    tick,
+                    InputEvent::Invert,
+                );
+                info!("Inverted canvas");
+            }
+
+            if input.key_pressed(VirtualKeyCode::H) {
+                model.show_hud = !model.show_hud;
+                info!("Show HUD {}", model.show_hud);
+            }
+
+            if input.key_pressed(VirtualKeyCode::G) {
+                model.show_grid = !model.show_grid;
+                info!("Show grid {}", model.show_grid);
+            }
+
+            if !replaying && input.key_pressed(VirtualKeyCode::A) {
+                model.accumulate_paint = !model.accumulate_paint;
+                info!("Accumulate paint {}", model.accumulate_paint);
+            }
+
+            if !replaying && input.key_pressed(VirtualKeyCode::E) {
+                model.erase_hard = !model.erase_hard;
+                info!("{} erase", if model.erase_hard { "Hard" } else { "Soft" });
+            }
+
+            if input.key_pressed(VirtualKeyCode::D) {
+                if let Err(e) = model.base_matrix.write_csv(std::io::stdout().lock()) {
+                    error!("Failed to write canvas as CSV: {}", e);
+                }
+            }
+
+            if !replaying && input.key_pressed(VirtualKeyCode::Space) {
+                model.paused = !model.paused;
+                info!("{}", if model.paused { "Paused" } else { "Unpaused" });
+            }
+
+            if !replaying && input.key_pressed(VirtualKeyCode::Period) {
+                perform(&mut model, input_recorder.as_mut(), tick, InputEvent::Step);
+            }
+
+            if input.key_pressed(VirtualKeyCode::S) {
+                let path = timestamped_path("png");
+                match model.save_png(&path) {
+                    Ok(()) => info!("Saved canvas to {}", path.display()),
+                    Err(e) => error!("Failed to save canvas to {}: {}", path.display(), e),
+                }
+            }
+
+            if input.key_pressed(VirtualKeyCode::R) {
+                match recorder.take() {
+                    Some(finished_recorder) => {
+                        let path = timestamped_path("gif");
+                        info!(
+                            "Stopped recording, writing {} frames to {}",
+                            finished_recorder.len(),
+                            path.display()
+                        );
+                        if let Err(e) = finished_recorder.finish(&path) {
+                            error!("Failed to save recording to {}: {}", path.display(), e);
+                        }
+                    }
+                    None => match Recorder::start(
+                        model.frame_size().0 as u32,
+                        model.frame_size().1 as u32,
+                    ) {
+                        Ok(new_recorder) => {
+                            info!("Started recording");
+                            recorder = Some(new_recorder);
+                        }
+                        Err(e) => error!("Failed to start recording: {}", e),
+                    },
+                }
+            }
+
+            if let Some(position) = input.mouse() {
+                // Map the cursor from window space into pixel buffer space
+                let (x, y) = match pixels.window_pos_to_pixel(position) {
+                    Ok((x, y)) => (x as f32, y as f32),
+                    Err((x, y)) => (x as f32, y as f32),
+                };
+                let buffer_xy = Vector2::new(x, y);
+
+                // Drag with the middle button to pan, the view moves a cell for every cell's worth of pixels the cursor
+                // moves
+                if input.mouse_held(2) {
+                    if let Some(previous_buffer_xy) = previous_buffer_xy {
+                        let pixels_per_cell = model.pixels_per_cell() as f32;
+                        model.pan_by((previous_buffer_xy - buffer_xy) * (1.0 / pixels_per_cell));
+                    }
+                }
+
+                previous_buffer_xy = Some(buffer_xy);
+                if !replaying {
+                    model.mouse_xy = model.buffer_to_canvas(buffer_xy);
+                }
+            }
+
+            let scroll = input.scroll_diff();
+            if scroll != 0.0 {
+                let zoom = if scroll > 0.0 {
+                    model.zoom + 1
+                } else {
+                    model.zoom.saturating_sub(1)
+                };
+                model.set_zoom(zoom);
+                info!("Zoom {}x", model.zoom);
+            }
+
+            // Resize the window. Pixels only resizes the surface, the pixel buffer keeps its dimensions and scales
+            // to fit, so the model is left at the size of the buffer.
             if let Some(size) = input.window_resized() {
                 pixels.resize(size.width, size.height);
             }
 
-            // Update internal state and request a redraw
+            // Update internal state as many times as the time the last frame took calls for, and request a redraw
             window.request_redraw();
-            model.update(frame_time);
+            for _ in 0..timestep.advance(frame_time) {
+                if let Some(replay) = replay.as_mut() {
+                    replay.apply(tick, &mut model);
+                }
+                if let Some(input_recorder) = input_recorder.as_mut() {
+                    input_recorder.observe(tick, &model);
+                }
+                model.update(timestep.step());
+                tick += 1;
+            }
+
+            if let Some(remaining) = max_fps.and_then(|max_fps| {
+                frame_budget_remaining(max_fps, time_of_last_frame_start.elapsed())
+            }) {
+                std::thread::sleep(remaining);
+            }
 
-            frame_time = time_of_last_frame_start.elapsed().as_secs_f32();
+            // Measured after sleeping, so the next tick advances by the time that really passed
+            frame_time = time_of_last_frame_start.elapsed();
             time_of_last_frame_start = Instant::now();
 
             frame_counter += 1;
 
-            if time_of_last_fps_counter_update.elapsed().as_secs() > 1 {
+            let time_since_fps_counter_update = time_of_last_fps_counter_update.elapsed();
+            if time_since_fps_counter_update.as_secs_f32() >= FPS_UPDATE_INTERVAL {
                 time_of_last_fps_counter_update = Instant::now();
-                let _ = fps_values.push(frame_counter);
+                let _ = fps_values
+                    .push(frame_counter as f32 / time_since_fps_counter_update.as_secs_f32());
                 frame_counter = 0;
 
-                let fps_sum: i32 = fps_values.iter().sum();
-                let avg_fps = fps_sum as f32 / fps_values.len() as f32;
-                info!("FPS {}", avg_fps.trunc());
+                let fps_sum: f32 = fps_values.iter().sum();
+                let avg_fps = fps_sum / fps_values.len() as f32;
+                model.fps = avg_fps;
+                debug!("FPS {}", avg_fps.trunc());
+                window.set_title(&format_title(
+                    &title,
+                    avg_fps,
+                    model.base_matrix.count_nonzero(),
+                ));
             }
         }
     })
 }
+
+/// A path in the working directory like `diffuser-<unix timestamp>.<extension>`
+fn timestamped_path(extension: &str) -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+
+    PathBuf::from(format!("diffuser-{}.{}", timestamp, extension))
+}
+
+/// Turns however long each frame takes into a steady number of updates a second, so the simulation runs at the same
+/// speed no matter how fast frames are drawn
+struct FixedTimestep {
+    updates_per_second: u32,
+    /// Time that's passed but hasn't been updated for yet, in updates
+    pending: f64,
+}
+
+impl FixedTimestep {
+    fn new(updates_per_second: u32) -> Self {
+        Self {
+            updates_per_second: updates_per_second.max(1),
+            pending: 0.0,
+        }
+    }
+
+    /// How long each update lasts, in seconds
+    fn step(&self) -> f32 {
+        1.0 / self.updates_per_second as f32
+    }
+
+    /// Account for `elapsed` more time passing and return how many updates are due, at most
+    /// `MAX_UPDATES_PER_FRAME`. Whatever's left over that isn't worth a whole update waits for the next call.
+    fn advance(&mut self, elapsed: Duration) -> usize {
+        self.pending += elapsed.as_secs_f64() * self.updates_per_second as f64;
+        // Leeway for durations being rounded to the nanosecond, so a frame that lasts one step counts as one
+        let due = (self.pending + 1e-6).floor();
+        self.pending = (self.pending - due).max(0.0);
+
+        if due as usize > MAX_UPDATES_PER_FRAME {
+            self.pending = 0.0;
+            MAX_UPDATES_PER_FRAME
+        } else {
+            due as usize
+        }
+    }
+}
+
+/// How much longer a frame that's taken `elapsed` so far has to last to keep to `max_fps`, or `None` if it's already
+/// used up its share of the second
+fn frame_budget_remaining(max_fps: u32, elapsed: Duration) -> Option<Duration> {
+    let budget = Duration::from_secs_f64(1.0 / max_fps.max(1) as f64);
+    budget
+        .checked_sub(elapsed)
+        .filter(|remaining| *remaining > Duration::from_secs(0))
+}
+
+/// The number keys along the top of the keyboard, which pick paint value presets
+const DIGIT_KEYS: [VirtualKeyCode; 10] = [
+    VirtualKeyCode::Key0,
+    VirtualKeyCode::Key1,
+    VirtualKeyCode::Key2,
+    VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4,
+    VirtualKeyCode::Key5,
+    VirtualKeyCode::Key6,
+    VirtualKeyCode::Key7,
+    VirtualKeyCode::Key8,
+    VirtualKeyCode::Key9,
+];
+
+/// Apply `event` to `model` the way a replay would, and record it if input is being recorded
+fn perform(
+    model: &mut Model,
+    input_recorder: Option<&mut InputRecorder>,
+    tick: usize,
+    event: InputEvent,
+) {
+    event.apply(model);
+    record(model, input_recorder, tick, event);
+}
+
+/// Record `event` as happening before the update on `tick`, if input is being recorded
+fn record(
+    model: &Model,
+    input_recorder: Option<&mut InputRecorder>,
+    tick: usize,
+    event: InputEvent,
+) {
+    if let Some(input_recorder) = input_recorder {
+        input_recorder.record(tick, model, event);
+    }
+}
+
+/// The paint value a number key picks, `1` through `9` are tenths of `max_value` and `0` is all of it. Any other key
+/// doesn't pick anything.
+fn preset_paint_value(key: VirtualKeyCode, max_value: f32) -> Option<f32> {
+    let tenths = match key {
+        VirtualKeyCode::Key1 => 1,
+        VirtualKeyCode::Key2 => 2,
+        VirtualKeyCode::Key3 => 3,
+        VirtualKeyCode::Key4 => 4,
+        VirtualKeyCode::Key5 => 5,
+        VirtualKeyCode::Key6 => 6,
+        VirtualKeyCode::Key7 => 7,
+        VirtualKeyCode::Key8 => 8,
+        VirtualKeyCode::Key9 => 9,
+        VirtualKeyCode::Key0 => 10,
+        _ => return None,
+    };
+
+    Some(max_value * tenths as f32 / 10.0)
+}
+
+/// The size the surface should be after switching to fullscreen on a monitor of `monitor_size`, or back to a window of
+/// `windowed_size`. If the monitor's size isn't known the surface keeps the window's size.
+fn surface_size(
+    fullscreen: bool,
+    monitor_size: Option<PhysicalSize<u32>>,
+    windowed_size: PhysicalSize<u32>,
+) -> PhysicalSize<u32> {
+    match (fullscreen, monitor_size) {
+        (true, Some(monitor_size)) => monitor_size,
+        _ => windowed_size,
+    }
+}
+
+/// The window title with an FPS and painted cell readout, like `Diffuser | 60 FPS | 1234 painted cells`
+fn format_title(title: &str, fps: f32, nonzero: usize) -> String {
+    format!(
+        "{} | {} FPS | {} painted cells",
+        title,
+        fps.trunc(),
+        nonzero
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::constants::DEFAULT_MAX_VALUE;
+
+    #[test]
+    fn test_preset_paint_value() {
+        assert_eq!(
+            Some(50.0),
+            preset_paint_value(VirtualKeyCode::Key1, DEFAULT_MAX_VALUE)
+        );
+        assert_eq!(
+            Some(450.0),
+            preset_paint_value(VirtualKeyCode::Key9, DEFAULT_MAX_VALUE)
+        );
+        assert_eq!(
+            Some(DEFAULT_MAX_VALUE),
+            preset_paint_value(VirtualKeyCode::Key0, DEFAULT_MAX_VALUE)
+        );
+        assert_eq!(Some(0.5), preset_paint_value(VirtualKeyCode::Key5, 1.0));
+        assert_eq!(
+            None,
+            preset_paint_value(VirtualKeyCode::Q, DEFAULT_MAX_VALUE)
+        );
+        assert!(DIGIT_KEYS
+            .iter()
+            .all(|key| preset_paint_value(*key, DEFAULT_MAX_VALUE).is_some()));
+    }
+
+    #[test]
+    fn test_surface_size() {
+        let monitor_size = PhysicalSize::new(1920, 1080);
+        let windowed_size = PhysicalSize::new(800, 600);
+
+        assert_eq!(
+            monitor_size,
+            surface_size(true, Some(monitor_size), windowed_size)
+        );
+        assert_eq!(
+            windowed_size,
+            surface_size(false, Some(monitor_size), windowed_size)
+        );
+        assert_eq!(windowed_size, surface_size(true, None, windowed_size));
+    }
+
+    #[test]
+    fn test_frame_budget_remaining() {
+        assert_eq!(
+            Some(Duration::from_millis(15)),
+            frame_budget_remaining(50, Duration::from_millis(5))
+        );
+        assert_eq!(
+            Some(Duration::from_millis(250)),
+            frame_budget_remaining(2, Duration::from_millis(250))
+        );
+        assert_eq!(None, frame_budget_remaining(50, Duration::from_millis(20)));
+        assert_eq!(None, frame_budget_remaining(50, Duration::from_millis(35)));
+    }
+
+    #[test]
+    fn test_fixed_timestep() {
+        let mut timestep = FixedTimestep::new(60);
+        assert_eq!(1.0 / 60.0, timestep.step());
+        assert_eq!(0, timestep.advance(Duration::from_millis(10)));
+        // The 10ms left over from last time makes this one worth a step
+        assert_eq!(1, timestep.advance(Duration::from_millis(10)));
+        assert_eq!(
+            MAX_UPDATES_PER_FRAME,
+            timestep.advance(Duration::from_secs(1))
+        );
+        // Time that was dropped isn't caught up on later
+        assert_eq!(0, timestep.advance(Duration::from_millis(0)));
+
+        // However the second is split up, it's worth the same number of updates
+        let mut timestep = FixedTimestep::new(60);
+        let updates: usize = (0..144)
+            .map(|_| timestep.advance(Duration::from_secs_f64(1.0 / 144.0)))
+            .sum();
+        assert_eq!(60, updates);
+
+        let mut timestep = FixedTimestep::new(30);
+        assert_eq!(3, timestep.advance(Duration::from_millis(100)));
+    }
+
+    #[test]
+    fn test_format_title_shows_fps_and_painted_cells() {
+        assert_eq!(
+            "Diffuser | 59 FPS | 1234 painted cells",
+            format_title("Diffuser", 59.8, 1234)
+        );
+    }
+}
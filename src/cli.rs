@@ -0,0 +1,537 @@
+use crate::app;
+use crate::config::SimulationConfig;
+use crate::constants::{
+    DEFAULT_MAX_VALUE, DEFAULT_RESOLUTION_H, DEFAULT_RESOLUTION_W, DEFAULT_TITLE,
+    DEFAULT_UPDATES_PER_SECOND, DEFAULT_VALUE_CUTOFF, MAX_RESOLUTION,
+};
+use crate::patterns::{Demo, Pattern};
+use crate::rect::Rect;
+use log::warn;
+use pixels::wgpu::PresentMode;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+// Environment variables that fill in for the defaults, so they can be experimented with without recompiling
+pub const ENV_DECAY: &str = "DIFFUSER_DECAY";
+pub const ENV_CUTOFF: &str = "DIFFUSER_CUTOFF";
+pub const ENV_MAX: &str = "DIFFUSER_MAX";
+pub const ENV_WIDTH: &str = "DIFFUSER_W";
+pub const ENV_HEIGHT: &str = "DIFFUSER_H";
+
+/// Options passed on the command line
+#[derive(Debug, Default, PartialEq)]
+pub struct Args {
+    /// An image drawn behind the canvas that never diffuses
+    pub background: Option<PathBuf>,
+    /// Overrides how much ink evaporates each second, only set from `DIFFUSER_DECAY`
+    pub decay_factor: Option<f32>,
+    /// A demo to seed the canvas with
+    pub demo: Option<Demo>,
+    /// Run the simulation without opening a window
+    pub headless: bool,
+    /// The height of the canvas in cells
+    pub height: Option<u32>,
+    /// An image to seed the canvas with
+    pub image: Option<PathBuf>,
+    /// The most frames the window draws each second, uncapped if it isn't given
+    pub max_fps: Option<u32>,
+    /// Overrides the most ink a cell can hold, only set from `DIFFUSER_MAX`
+    pub max_value: Option<f32>,
+    /// Where a headless run saves its final frame
+    pub out: Option<PathBuf>,
+    /// How finished frames are shown, vsynced if it isn't given
+    pub present_mode: Option<PresentMode>,
+    /// Where to save a recording of the mouse input, when the window is closed
+    pub record_input: Option<PathBuf>,
+    /// A recording of mouse input to play back
+    pub replay: Option<PathBuf>,
+    /// Seeds the random number generator, for runs that can be reproduced
+    pub rng_seed: Option<u64>,
+    /// A pattern to seed the canvas with
+    pub seed: Option<Pattern>,
+    /// How many pixels wide each cell is drawn, the canvas is this many times smaller than the window
+    pub sim_scale: Option<u32>,
+    /// How many ticks a headless run lasts
+    pub ticks: Option<usize>,
+    pub title: Option<String>,
+    /// How many times a second the window updates the simulation
+    pub updates_per_second: Option<u32>,
+    /// Overrides the least ink a cell can hold without being emptied, only set from `DIFFUSER_CUTOFF`
+    pub value_cutoff: Option<f32>,
+    /// The width of the canvas in cells
+    pub width: Option<u32>,
+}
+
+impl Args {
+    /// Parse arguments, not including the name of the program
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, app::Error> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--background" => {
+                    parsed.background = Some(PathBuf::from(value_for(&arg, args.next())?))
+                }
+                "--demo" => parsed.demo = Some(parse_name_for(&arg, args.next())?),
+                "--headless" => parsed.headless = true,
+                "--height" => parsed.height = Some(parse_dimension(&arg, args.next())?),
+                "--image" => parsed.image = Some(PathBuf::from(value_for(&arg, args.next())?)),
+                "--max-fps" => parsed.max_fps = Some(parse_nonzero(&arg, args.next())?),
+                "--out" => parsed.out = Some(PathBuf::from(value_for(&arg, args.next())?)),
+                "--present-mode" => {
+                    parsed.present_mode = Some(parse_present_mode(&arg, args.next())?)
+                }
+                "--record-input" => {
+                    parsed.record_input = Some(PathBuf::from(value_for(&arg, args.next())?))
+                }
+                "--replay" => parsed.replay = Some(PathBuf::from(value_for(&arg, args.next())?)),
+                "--rng-seed" => parsed.rng_seed = Some(parse_value_for(&arg, args.next())?),
+                "--seed" => parsed.seed = Some(parse_name_for(&arg, args.next())?),
+                "--sim-scale" => parsed.sim_scale = Some(parse_nonzero(&arg, args.next())?),
+                "--ticks" => parsed.ticks = Some(parse_value_for(&arg, args.next())?),
+                "--title" => parsed.title = Some(value_for(&arg, args.next())?),
+                "--updates-per-second" => {
+                    parsed.updates_per_second = Some(parse_nonzero(&arg, args.next())?)
+                }
+                "--width" => parsed.width = Some(parse_dimension(&arg, args.next())?),
+                _ => return Err(app::Error::UnknownArgument { argument: arg }),
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    /// Fill in whatever wasn't given on the command line from the `DIFFUSER_*` environment variables, looking each one
+    /// up with `var`. Values that don't parse are warned about and left at their defaults. The max has to be more than
+    /// zero and the cutoff less than the max, otherwise both are warned about and left at their defaults.
+    pub fn apply_env_overrides(&mut self, var: impl Fn(&str) -> Option<String>) {
+        let amount = |value: &f32| value.is_finite() && *value >= 0.0;
+        let positive = |value: &f32| value.is_finite() && *value > 0.0;
+        let dimension = |value: &u32| *value > 0 && *value <= MAX_RESOLUTION;
+
+        self.decay_factor = self
+            .decay_factor
+            .or_else(|| env_value(ENV_DECAY, var(ENV_DECAY), amount));
+        self.value_cutoff = self
+            .value_cutoff
+            .or_else(|| env_value(ENV_CUTOFF, var(ENV_CUTOFF), amount));
+        self.max_value = self
+            .max_value
+            .or_else(|| env_value(ENV_MAX, var(ENV_MAX), positive));
+        let cutoff = self.value_cutoff.unwrap_or(DEFAULT_VALUE_CUTOFF);
+        let max = self.max_value.unwrap_or(DEFAULT_MAX_VALUE);
+        if cutoff >= max {
+            warn!(
+                "Ignoring {} and {}, the cutoff ({}) has to be less than the max ({})",
+                ENV_CUTOFF, ENV_MAX, cutoff, max
+            );
+            self.value_cutoff = None;
+            self.max_value = None;
+        }
+        self.width = self
+            .width
+            .or_else(|| env_value(ENV_WIDTH, var(ENV_WIDTH), dimension));
+        self.height = self
+            .height
+            .or_else(|| env_value(ENV_HEIGHT, var(ENV_HEIGHT), dimension));
+    }
+
+    /// The size of the canvas as `(width, height)`. A dimension that wasn't given keeps its default.
+    pub fn resolution(&self) -> (u32, u32) {
+        (
+            self.width.unwrap_or(DEFAULT_RESOLUTION_W),
+            self.height.unwrap_or(DEFAULT_RESOLUTION_H),
+        )
+    }
+
+    /// How many pixels wide each cell is drawn, 1 if it isn't given
+    pub fn sim_scale(&self) -> u32 {
+        self.sim_scale.unwrap_or(1)
+    }
+
+    /// How many times a second the window updates the simulation, `DEFAULT_UPDATES_PER_SECOND` if it isn't given
+    pub fn updates_per_second(&self) -> u32 {
+        self.updates_per_second
+            .unwrap_or(DEFAULT_UPDATES_PER_SECOND)
+    }
+
+    /// How long each update of the simulation lasts in seconds, which is one over `updates_per_second`
+    pub fn step(&self) -> f32 {
+        1.0 / self.updates_per_second() as f32
+    }
+
+    /// The size of the canvas in cells as `(width, height)`, which is the resolution divided by `sim_scale`
+    pub fn canvas_size(&self) -> (u32, u32) {
+        let (width, height) = self.resolution();
+        let sim_scale = self.sim_scale();
+        ((width / sim_scale).max(1), (height / sim_scale).max(1))
+    }
+
+    /// The size of the pixel buffer as `(width, height)`, which is the canvas scaled back up by `sim_scale`. It comes
+    /// out a little smaller than the resolution if that doesn't divide evenly.
+    pub fn frame_size(&self) -> (u32, u32) {
+        let (width, height) = self.canvas_size();
+        (width * self.sim_scale(), height * self.sim_scale())
+    }
+
+    /// The config to create the model with
+    pub fn config(&self) -> SimulationConfig {
+        let (width, height) = self.canvas_size();
+        let defaults =
+            SimulationConfig::with_resolution(Rect::new(height as usize, width as usize));
+        SimulationConfig {
+            decay_factor: self.decay_factor.unwrap_or(defaults.decay_factor),
+            max_value: self.max_value.unwrap_or(defaults.max_value),
+            rng_seed: self.rng_seed,
+            value_cutoff: self.value_cutoff.unwrap_or(defaults.value_cutoff),
+            ..defaults
+        }
+    }
+
+    /// The present mode to create the pixel buffer with, `Fifo` if none was given, which waits for vsync
+    pub fn present_mode(&self) -> PresentMode {
+        self.present_mode.unwrap_or(PresentMode::Fifo)
+    }
+
+    pub fn title(&self) -> &str {
+        self.title.as_deref().unwrap_or(DEFAULT_TITLE)
+    }
+}
+
+/// Parse the environment variable `name`, if it's set. Values that don't parse or aren't `valid` are warned about and
+/// treated as if the variable wasn't set, so the default is used instead.
+fn env_value<T: FromStr>(
+    name: &str,
+    value: Option<String>,
+    valid: impl Fn(&T) -> bool,
+) -> Option<T> {
+    let value = value?;
+    match value.trim().parse() {
+        Ok(parsed) if valid(&parsed) => Some(parsed),
+        _ => {
+            warn!("Ignoring {}=\"{}\", it isn't a valid value", name, value);
+            None
+        }
+    }
+}
+
+fn value_for(flag: &str, value: Option<String>) -> Result<String, app::Error> {
+    value.ok_or_else(|| app::Error::MissingArgumentValue {
+        flag: flag.to_owned(),
+    })
+}
+
+fn parse_value_for<T: FromStr>(flag: &str, value: Option<String>) -> Result<T, app::Error> {
+    let value = value_for(flag, value)?;
+    value.parse().map_err(|_| app::Error::InvalidArgumentValue {
+        flag: flag.to_owned(),
+        value,
+    })
+}
+
+/// Like `parse_value_for`, for values whose own error already says what went wrong, like an unknown pattern name
+fn parse_name_for<T: FromStr<Err = app::Error>>(
+    flag: &str,
+    value: Option<String>,
+) -> Result<T, app::Error> {
+    value_for(flag, value)?.parse()
+}
+
+/// A canvas dimension has to be at least one cell and no more than `MAX_RESOLUTION` cells
+fn parse_dimension(flag: &str, value: Option<String>) -> Result<u32, app::Error> {
+    let dimension: u32 = parse_value_for(flag, value)?;
+    if dimension == 0 || dimension > MAX_RESOLUTION {
+        return Err(app::Error::InvalidArgumentValue {
+            flag: flag.to_owned(),
+            value: dimension.to_string(),
+        });
+    }
+
+    Ok(dimension)
+}
+
+/// A frame rate cap has to be at least one frame a second, and a sim scale at least one pixel per cell
+fn parse_nonzero(flag: &str, value: Option<String>) -> Result<u32, app::Error> {
+    let parsed: u32 = parse_value_for(flag, value)?;
+    if parsed == 0 {
+        return Err(app::Error::InvalidArgumentValue {
+            flag: flag.to_owned(),
+            value: parsed.to_string(),
+        });
+    }
+
+    Ok(parsed)
+}
+
+/// `immediate` shows frames as soon as they're done, which may tear, `fifo` waits for vsync, and `mailbox` waits for
+/// vsync but always shows the newest frame
+fn parse_present_mode(flag: &str, value: Option<String>) -> Result<PresentMode, app::Error> {
+    let value = value_for(flag, value)?;
+    match value.as_str() {
+        "immediate" => Ok(PresentMode::Immediate),
+        "fifo" => Ok(PresentMode::Fifo),
+        "mailbox" => Ok(PresentMode::Mailbox),
+        _ => Err(app::Error::InvalidArgumentValue {
+            flag: flag.to_owned(),
+            value,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, app::Error> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_no_args() {
+        assert_eq!(Args::default(), parse(&[]).unwrap());
+    }
+
+    #[test]
+    fn test_image() {
+        let args = parse(&["--image", "photo.png"]).unwrap();
+        assert_eq!(Some(PathBuf::from("photo.png")), args.image);
+    }
+
+    #[test]
+    fn test_background() {
+        let args = parse(&["--background", "paper.png", "--image", "photo.png"]).unwrap();
+        assert_eq!(Some(PathBuf::from("paper.png")), args.background);
+        assert_eq!(Some(PathBuf::from("photo.png")), args.image);
+    }
+
+    #[test]
+    fn test_replay_and_record_input() {
+        let args = parse(&["--replay", "session.txt", "--record-input", "new.txt"]).unwrap();
+        assert_eq!(Some(PathBuf::from("session.txt")), args.replay);
+        assert_eq!(Some(PathBuf::from("new.txt")), args.record_input);
+    }
+
+    #[test]
+    fn test_headless() {
+        let args = parse(&["--headless", "--ticks", "100", "--out", "final.png"]).unwrap();
+        assert!(args.headless);
+        assert_eq!(Some(100), args.ticks);
+        assert_eq!(Some(PathBuf::from("final.png")), args.out);
+    }
+
+    #[test]
+    fn test_max_fps() {
+        assert_eq!(Some(30), parse(&["--max-fps", "30"]).unwrap().max_fps);
+        assert!(matches!(
+            parse(&["--max-fps", "0"]),
+            Err(app::Error::InvalidArgumentValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_updates_per_second() {
+        assert_eq!(
+            120,
+            parse(&["--updates-per-second", "120"])
+                .unwrap()
+                .updates_per_second()
+        );
+        assert_eq!(
+            DEFAULT_UPDATES_PER_SECOND,
+            Args::default().updates_per_second()
+        );
+        assert_eq!(
+            1.0 / 30.0,
+            parse(&["--updates-per-second", "30"]).unwrap().step()
+        );
+        assert!(matches!(
+            parse(&["--updates-per-second", "0"]),
+            Err(app::Error::InvalidArgumentValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_sim_scale() {
+        let args = parse(&["--width", "1920", "--height", "1081", "--sim-scale", "2"]).unwrap();
+        assert_eq!((960, 540), args.canvas_size());
+        assert_eq!((1920, 1080), args.frame_size());
+        assert_eq!(960, args.config().resolution.w());
+        assert_eq!(1, Args::default().sim_scale());
+        assert!(matches!(
+            parse(&["--sim-scale", "0"]),
+            Err(app::Error::InvalidArgumentValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_env_value() {
+        let any = |_: &f32| true;
+        assert_eq!(
+            Some(0.25),
+            env_value("DIFFUSER_DECAY", Some("0.25".to_owned()), any)
+        );
+        assert_eq!(
+            None,
+            env_value("DIFFUSER_DECAY", Some("fast".to_owned()), any)
+        );
+        assert_eq!(None, env_value::<f32>("DIFFUSER_DECAY", None, any));
+        assert_eq!(
+            None,
+            env_value("DIFFUSER_W", Some("0".to_owned()), |value: &u32| *value > 0)
+        );
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let env = |name: &str| match name {
+            "DIFFUSER_DECAY" => Some("2.5".to_owned()),
+            "DIFFUSER_MAX" => Some("-1".to_owned()),
+            "DIFFUSER_W" => Some("320".to_owned()),
+            "DIFFUSER_H" => Some("200".to_owned()),
+            _ => None,
+        };
+        let mut args = parse(&["--height", "100"]).unwrap();
+        args.apply_env_overrides(env);
+        let config = args.config();
+
+        assert_eq!(2.5, config.decay_factor);
+        assert_eq!(SimulationConfig::default().max_value, config.max_value);
+        assert_eq!(
+            SimulationConfig::default().value_cutoff,
+            config.value_cutoff
+        );
+        // Flags on the command line win over the environment
+        assert_eq!((320, 100), args.resolution());
+    }
+
+    #[test]
+    fn test_env_overrides_reject_a_cutoff_that_isnt_below_the_max() {
+        let defaults = SimulationConfig::default();
+
+        let mut args = Args::default();
+        args.apply_env_overrides(|name: &str| match name {
+            "DIFFUSER_MAX" => Some("0".to_owned()),
+            _ => None,
+        });
+        assert_eq!(defaults.max_value, args.config().max_value);
+
+        for (cutoff, max) in [("10", "10"), ("20", "10")].iter() {
+            let mut args = Args::default();
+            args.apply_env_overrides(|name: &str| match name {
+                "DIFFUSER_CUTOFF" => Some(cutoff.to_string()),
+                "DIFFUSER_MAX" => Some(max.to_string()),
+                _ => None,
+            });
+            let config = args.config();
+            assert_eq!(defaults.value_cutoff, config.value_cutoff);
+            assert_eq!(defaults.max_value, config.max_value);
+        }
+
+        let mut args = Args::default();
+        args.apply_env_overrides(|name: &str| match name {
+            "DIFFUSER_CUTOFF" => Some("1".to_owned()),
+            "DIFFUSER_MAX" => Some("10".to_owned()),
+            _ => None,
+        });
+        let config = args.config();
+        assert_eq!(1.0, config.value_cutoff);
+        assert_eq!(10.0, config.max_value);
+    }
+
+    #[test]
+    fn test_present_mode() {
+        assert_eq!(PresentMode::Fifo, Args::default().present_mode());
+        for (name, present_mode) in [
+            ("immediate", PresentMode::Immediate),
+            ("fifo", PresentMode::Fifo),
+            ("mailbox", PresentMode::Mailbox),
+        ]
+        .iter()
+        {
+            let args = parse(&["--present-mode", name]).unwrap();
+            assert_eq!(*present_mode, args.present_mode());
+        }
+
+        assert!(matches!(
+            parse(&["--present-mode", "vsync"]),
+            Err(app::Error::InvalidArgumentValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_seed() {
+        let args = parse(&["--seed", "circle"]).unwrap();
+        assert_eq!(Some(Pattern::Circle), args.seed);
+        assert!(matches!(
+            parse(&["--seed", "spiral"]),
+            Err(app::Error::UnknownPattern { .. })
+        ));
+    }
+
+    #[test]
+    fn test_demo() {
+        assert_eq!(Some(Demo::Drop), parse(&["--demo", "drop"]).unwrap().demo);
+        assert!(matches!(
+            parse(&["--demo", "rain"]),
+            Err(app::Error::UnknownDemo { .. })
+        ));
+    }
+
+    #[test]
+    fn test_rng_seed() {
+        let args = parse(&["--rng-seed", "1234", "--width", "320"]).unwrap();
+        let config = args.config();
+        assert_eq!(Some(1234), config.rng_seed);
+        assert_eq!(320, config.resolution.w());
+        assert_eq!(None, Args::default().config().rng_seed);
+    }
+
+    #[test]
+    fn test_resolution_and_title() {
+        let args = parse(&["--width", "320", "--height", "200", "--title", "Ink"]).unwrap();
+        assert_eq!((320, 200), args.resolution());
+        assert_eq!("Ink", args.title());
+    }
+
+    #[test]
+    fn test_defaults() {
+        let args = parse(&["--height", "200"]).unwrap();
+        assert_eq!((DEFAULT_RESOLUTION_W, 200), args.resolution());
+        assert_eq!(DEFAULT_TITLE, args.title());
+    }
+
+    #[test]
+    fn test_invalid_dimensions() {
+        for dimension in &["0", "-5", "100000"] {
+            assert!(matches!(
+                parse(&["--width", dimension]),
+                Err(app::Error::InvalidArgumentValue { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_invalid_value() {
+        assert!(matches!(
+            parse(&["--ticks", "lots"]),
+            Err(app::Error::InvalidArgumentValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_missing_value() {
+        assert!(matches!(
+            parse(&["--image"]),
+            Err(app::Error::MissingArgumentValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_unknown_argument() {
+        assert!(matches!(
+            parse(&["--nope"]),
+            Err(app::Error::UnknownArgument { .. })
+        ));
+    }
+}
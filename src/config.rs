@@ -0,0 +1,43 @@
+use crate::constants::{
+    DEFAULT_DECAY_FACTOR, DEFAULT_MAX_VALUE, DEFAULT_RESOLUTION_H, DEFAULT_RESOLUTION_W,
+    DEFAULT_VALUE_CUTOFF,
+};
+use crate::rect::Rect;
+
+/// The parameters a `Model` is created with
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SimulationConfig {
+    /// How much ink evaporates from every cell each second
+    pub decay_factor: f32,
+    /// The most ink a cell can hold
+    pub max_value: f32,
+    /// The size of the canvas the model starts out with, in cells
+    pub resolution: Rect<usize>,
+    /// Seeds the model's random number generator so randomized features play out the same way every time. When it's
+    /// `None` the generator is seeded from entropy.
+    pub rng_seed: Option<u64>,
+    /// Cells with less ink than this are emptied instead of spilling over
+    pub value_cutoff: f32,
+}
+
+impl SimulationConfig {
+    /// The default config with a different canvas size
+    pub fn with_resolution(resolution: Rect<usize>) -> Self {
+        Self {
+            resolution,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            decay_factor: DEFAULT_DECAY_FACTOR,
+            max_value: DEFAULT_MAX_VALUE,
+            resolution: Rect::new(DEFAULT_RESOLUTION_H as usize, DEFAULT_RESOLUTION_W as usize),
+            rng_seed: None,
+            value_cutoff: DEFAULT_VALUE_CUTOFF,
+        }
+    }
+}
@@ -1,6 +1,91 @@
 pub const DEFAULT_RESOLUTION_W: u32 = 800;
 pub const DEFAULT_RESOLUTION_H: u32 = 600;
+/// The largest the canvas can be on either side, which is as big as the pixel buffer's texture can get
+pub const MAX_RESOLUTION: u32 = 8192;
+pub const DEFAULT_TITLE: &str = "Diffuser";
 
 pub const DEFAULT_DECAY_FACTOR: f32 = 0.5;
+/// The longest a single tick is allowed to last, in seconds. Without a cap, a long stall (a breakpoint, dragging the
+/// window) would decay the whole canvas away in one tick.
+pub const MAX_FRAME_TIME: f32 = 0.1;
+pub const DECAY_FACTOR_STEP: f32 = 0.05;
+/// The biggest fraction of its ink a cell can lose a second with exponential decay. Any more and the whole canvas would
+/// be gone after a single tick.
+pub const MAX_EXPONENTIAL_DECAY_RATE: f32 = 0.95;
 pub const DEFAULT_MAX_VALUE: f32 = 500.0;
 pub const DEFAULT_VALUE_CUTOFF: f32 = 0.0001;
+/// Diffusion stops once the total change across the canvas stays below this for `SETTLE_TICKS` ticks in a row
+pub const DEFAULT_SETTLE_EPSILON: f32 = 0.001;
+pub const SETTLE_TICKS: usize = 10;
+/// How far ink spreads each tick with the Gaussian diffusion kernel, in cells
+pub const DEFAULT_GAUSSIAN_SIGMA: f32 = 0.8;
+/// How much of the difference between a cell and the average of its neighbours anti-diffusion adds each tick
+pub const ANTI_DIFFUSION_STRENGTH: f32 = 0.25;
+
+pub const DEFAULT_BRUSH_RADIUS: usize = 1;
+/// A hard brush paints a solid disc, softer brushes feather out around the edge
+pub const DEFAULT_BRUSH_HARDNESS: f32 = 1.0;
+/// How many times over `paint_value` the brush lays down each second when paint accumulates
+pub const DEFAULT_FLOW_RATE: f32 = 2.0;
+/// How much ink the soft eraser takes out of a cell each time it passes over it
+pub const DEFAULT_ERASE_STRENGTH: f32 = 50.0;
+/// How far a cell's value can be from the value of the cell a flood fill started on and still get filled
+pub const FLOOD_FILL_TOLERANCE: f32 = 0.01;
+/// The fraction of cells that get a drop of noise each tick while noise is turned on
+pub const DEFAULT_NOISE_RATE: f32 = 0.001;
+/// How much ink each drop of noise adds to a cell
+pub const DEFAULT_NOISE_AMOUNT: f32 = 50.0;
+
+/// Gamma applied to cell values before they're drawn, at 1.0 they're drawn as they are
+pub const DEFAULT_GAMMA: f32 = 1.0;
+pub const GAMMA_STEP: f32 = 0.1;
+/// Gamma is kept within this range, so it can never reach zero or go negative
+pub const MIN_GAMMA: f32 = 0.1;
+pub const MAX_GAMMA: f32 = 10.0;
+/// What `RenderMode::next` switches to, as a fraction of a full cell
+pub const DEFAULT_THRESHOLD: f32 = 0.5;
+pub const DEFAULT_POSTERIZE_LEVELS: u8 = 4;
+/// How many pixels wide each cell can be drawn
+pub const MIN_ZOOM: usize = 1;
+pub const MAX_ZOOM: usize = 16;
+/// How many cells apart the lines of the grid overlay are drawn
+pub const DEFAULT_GRID_SPACING: usize = 8;
+/// The grid overlay is this color, mixed with whatever's drawn under it so it stays faint
+pub const GRID_COLOR: [u8; 3] = [0x80, 0x80, 0x80];
+pub const GRID_OPACITY: f32 = 0.25;
+
+/// The colors that can be painted with in RGB mode, as fractions of the max value of each channel
+pub const PAINT_COLORS: [[f32; 3]; 7] = [
+    [1.0, 1.0, 1.0],
+    [1.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0],
+    [0.0, 0.0, 1.0],
+    [1.0, 1.0, 0.0],
+    [0.0, 1.0, 1.0],
+    [1.0, 0.0, 1.0],
+];
+
+/// How many times a second the window updates the simulation, however fast it draws
+pub const DEFAULT_UPDATES_PER_SECOND: u32 = 60;
+/// The most updates a single frame catches up on. Time beyond that is dropped, so a machine that can't keep up slows
+/// the simulation down rather than falling further and further behind.
+pub const MAX_UPDATES_PER_FRAME: usize = 8;
+
+/// How often the FPS readout in the window title is refreshed, in seconds
+pub const FPS_UPDATE_INTERVAL: f32 = 1.0;
+
+pub const RECORDER_FPS: f32 = 20.0;
+pub const DEFAULT_MAX_RECORDED_FRAMES: usize = 100;
+
+pub const DEFAULT_HEADLESS_TICKS: usize = 100;
+
+/// The chance of each cell starting out full of ink with `--seed random`
+pub const DEFAULT_SEED_DENSITY: f32 = 0.1;
+/// How many cells wide each square is with `--seed checkerboard`
+pub const DEFAULT_CHECKERBOARD_SIZE: usize = 16;
+
+pub const DEFAULT_MAX_UNDO_DEPTH: usize = 32;
+
+/// The least time between two messages about painting at the debug log level, in milliseconds. Every message is
+/// logged at the trace level.
+pub const PAINT_LOG_INTERVAL_MS: u64 = 250;
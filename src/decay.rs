@@ -0,0 +1,281 @@
+//! The last step of a tick of diffusion, where every cell decays and the result is clamped to the range a cell can
+//! hold. With the `simd` feature it's done eight cells at a time.
+
+use rayon::prelude::*;
+
+/// How many cells each rayon task works through. Big enough that splitting up the work doesn't cost more than it saves.
+const CHUNK_LEN: usize = 4096;
+
+/// How much ink a cell loses in a single tick
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Decay {
+    /// Subtract this much from every cell
+    Linear(f32),
+    /// Multiply every cell by this factor, so cells fade in proportion to how much they hold
+    Exponential(f32),
+}
+
+impl Decay {
+    /// `value` once it's decayed, with the decay made `multiplier` times as strong
+    pub fn apply(self, value: f32, multiplier: f32) -> f32 {
+        match self {
+            Decay::Linear(amount) => value - amount * multiplier,
+            Decay::Exponential(factor) => value * factor.powf(multiplier),
+        }
+    }
+}
+
+/// Decay every cell of `next` and clamp it to `[0, max_value]`. If there's a `decay_map`, each cell's decay is made as
+/// many times as strong as its value in the map, it's ignored unless it's as long as `next`. Returns the total absolute
+/// difference between `next` once it's decayed and `current`.
+pub fn decay_and_clamp(
+    next: &mut [f32],
+    current: &[f32],
+    decay: Decay,
+    decay_map: Option<&[f32]>,
+    max_value: f32,
+) -> f32 {
+    fill_decay_and_clamp(next, current, decay, decay_map, max_value, |_, _| ())
+}
+
+/// Like `decay_and_clamp`, but each chunk of `next` is filled in by `fill` first, which is given the index of the
+/// chunk's first cell. Decaying a chunk straight after it's filled in, while it's still in cache, saves making a second
+/// pass over the whole of `next`.
+pub fn fill_decay_and_clamp<F>(
+    next: &mut [f32],
+    current: &[f32],
+    decay: Decay,
+    decay_map: Option<&[f32]>,
+    max_value: f32,
+    fill: F,
+) -> f32
+where
+    F: Fn(usize, &mut [f32]) + Sync,
+{
+    assert_eq!(next.len(), current.len());
+    let decay_map = decay_map.filter(|decay_map| decay_map.len() == next.len());
+
+    next.par_chunks_mut(CHUNK_LEN)
+        .zip(current.par_chunks(CHUNK_LEN))
+        .enumerate()
+        .map(|(chunk_index, (next, current))| {
+            let start = chunk_index * CHUNK_LEN;
+            let decay_map = decay_map.map(|decay_map| &decay_map[start..start + next.len()]);
+
+            fill(start, next);
+            decay_chunk(next, current, decay, decay_map, max_value)
+        })
+        .sum()
+}
+
+#[cfg(feature = "simd")]
+fn decay_chunk(
+    next: &mut [f32],
+    current: &[f32],
+    decay: Decay,
+    decay_map: Option<&[f32]>,
+    max_value: f32,
+) -> f32 {
+    simd::decay_chunk(next, current, decay, decay_map, max_value)
+}
+
+#[cfg(not(feature = "simd"))]
+fn decay_chunk(
+    next: &mut [f32],
+    current: &[f32],
+    decay: Decay,
+    decay_map: Option<&[f32]>,
+    max_value: f32,
+) -> f32 {
+    scalar::decay_chunk(next, current, decay, decay_map, max_value)
+}
+
+mod scalar {
+    use super::Decay;
+
+    /// `decay_and_clamp` for a single chunk, one cell at a time. `decay_map` has to be as long as `next` if it's given.
+    pub fn decay_chunk(
+        next: &mut [f32],
+        current: &[f32],
+        decay: Decay,
+        decay_map: Option<&[f32]>,
+        max_value: f32,
+    ) -> f32 {
+        let mut change = 0.0;
+        for (index, (next_value, value)) in next.iter_mut().zip(current).enumerate() {
+            let multiplier = decay_map.map_or(1.0, |decay_map| decay_map[index]);
+            *next_value = decay.apply(*next_value, multiplier).clamp(0.0, max_value);
+            change += (*next_value - value).abs();
+        }
+
+        change
+    }
+}
+
+#[cfg(feature = "simd")]
+mod simd {
+    use super::Decay;
+    use std::convert::TryInto;
+    use wide::f32x8;
+
+    const LANES: usize = 8;
+
+    fn load(cells: &[f32]) -> f32x8 {
+        let lanes: [f32; LANES] = cells.try_into().expect("a whole vector's worth of cells");
+        f32x8::from(lanes)
+    }
+
+    /// `decay_and_clamp` for a single chunk, eight cells at a time. Whatever's left over that doesn't fill a vector is
+    /// done one cell at a time. The cells come out the same as they do from the scalar version. Exponential decay with
+    /// a decay map needs a power per cell, so it's left to the scalar version.
+    pub fn decay_chunk(
+        next: &mut [f32],
+        current: &[f32],
+        decay: Decay,
+        decay_map: Option<&[f32]>,
+        max_value: f32,
+    ) -> f32 {
+        if let (Decay::Exponential(_), Some(_)) = (decay, decay_map) {
+            return super::scalar::decay_chunk(next, current, decay, decay_map, max_value);
+        }
+
+        let vectorized_len = next.len() / LANES * LANES;
+        let (zero, max_value_lanes) = (f32x8::ZERO, f32x8::splat(max_value));
+
+        let mut change = f32x8::ZERO;
+        for start in (0..vectorized_len).step_by(LANES) {
+            let range = start..start + LANES;
+            let cells = load(&next[range.clone()]);
+            let decayed = match (decay, decay_map) {
+                (Decay::Linear(amount), Some(decay_map)) => {
+                    cells - f32x8::splat(amount) * load(&decay_map[range.clone()])
+                }
+                (Decay::Linear(amount), None) => cells - f32x8::splat(amount),
+                (Decay::Exponential(factor), _) => cells * f32x8::splat(factor),
+            };
+
+            let decayed = decayed.max(zero).min(max_value_lanes);
+            next[range.clone()].copy_from_slice(&decayed.to_array());
+            change += (decayed - load(&current[range])).abs();
+        }
+
+        change.reduce_add()
+            + super::scalar::decay_chunk(
+                &mut next[vectorized_len..],
+                &current[vectorized_len..],
+                decay,
+                decay_map.map(|decay_map| &decay_map[vectorized_len..]),
+                max_value,
+            )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn random_cells(rng: &mut StdRng, len: usize, max: f32) -> Vec<f32> {
+        (0..len).map(|_| rng.gen_range(-max, max * 2.0)).collect()
+    }
+
+    #[test]
+    fn test_decay_and_clamp() {
+        let mut next = vec![5.0, 0.5, 600.0, 3.0];
+        let current = vec![4.0, 1.0, 500.0, 3.0];
+        let change = decay_and_clamp(&mut next, &current, Decay::Linear(1.0), None, 500.0);
+
+        assert_eq!(vec![4.0, 0.0, 500.0, 2.0], next);
+        assert_eq!(2.0, change);
+    }
+
+    #[test]
+    fn test_decay_map_scales_decay() {
+        let mut next = vec![5.0, 5.0, 5.0];
+        let current = next.clone();
+        decay_and_clamp(
+            &mut next,
+            &current,
+            Decay::Linear(1.0),
+            Some(&[0.0, 1.0, 2.5]),
+            500.0,
+        );
+
+        assert_eq!(vec![5.0, 4.0, 2.5], next);
+    }
+
+    #[test]
+    fn test_exponential_decay_scales_cells() {
+        let mut next = vec![400.0, 1.0, 0.0];
+        let current = next.clone();
+        decay_and_clamp(&mut next, &current, Decay::Exponential(0.5), None, 500.0);
+        assert_eq!(vec![200.0, 0.5, 0.0], next);
+
+        // A cell with a multiplier of 2 decays twice over
+        let mut next = current.clone();
+        let decay_map = [2.0, 0.0, 1.0];
+        decay_and_clamp(
+            &mut next,
+            &current,
+            Decay::Exponential(0.5),
+            Some(&decay_map),
+            500.0,
+        );
+        assert_eq!(vec![100.0, 1.0, 0.0], next);
+    }
+
+    #[test]
+    #[cfg(feature = "simd")]
+    fn test_simd_matches_scalar() {
+        let mut rng = StdRng::seed_from_u64(64);
+        // Not a multiple of the vector width, so the leftover cells are covered too
+        let len = CHUNK_LEN + 13;
+        let next = random_cells(&mut rng, len, 500.0);
+        let current = random_cells(&mut rng, len, 500.0);
+        let decay_map = random_cells(&mut rng, len, 2.0);
+
+        let decays = [Decay::Linear(0.7), Decay::Exponential(0.7)];
+        let decay_maps = [None, Some(decay_map.as_slice())];
+        for (decay, decay_map) in decays
+            .iter()
+            .flat_map(|decay| decay_maps.iter().map(move |decay_map| (*decay, *decay_map)))
+        {
+            let mut scalar_next = next.clone();
+            let mut simd_next = next.clone();
+            let scalar_change =
+                scalar::decay_chunk(&mut scalar_next, &current, decay, decay_map, 500.0);
+            let simd_change = simd::decay_chunk(&mut simd_next, &current, decay, decay_map, 500.0);
+
+            assert_eq!(scalar_next, simd_next);
+            assert!((scalar_change - simd_change).abs() <= scalar_change * 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_chunks_line_up_with_decay_map() {
+        let mut rng = StdRng::seed_from_u64(64);
+        let len = CHUNK_LEN * 2 + 5;
+        let current = random_cells(&mut rng, len, 500.0);
+        let decay_map = random_cells(&mut rng, len, 2.0);
+
+        let mut next = current.clone();
+        let mut expected = current.clone();
+        decay_and_clamp(
+            &mut next,
+            &current,
+            Decay::Linear(0.7),
+            Some(&decay_map),
+            500.0,
+        );
+        scalar::decay_chunk(
+            &mut expected,
+            &current,
+            Decay::Linear(0.7),
+            Some(&decay_map),
+            500.0,
+        );
+
+        assert_eq!(expected, next);
+    }
+}
@@ -0,0 +1,123 @@
+use crate::app;
+use crate::cli::Args;
+use crate::constants::DEFAULT_HEADLESS_TICKS;
+use crate::model::Model;
+use crate::replay::Replay;
+use log::info;
+
+/// Advance the simulation `ticks` times without a window, as if every frame took exactly `step` seconds
+pub fn run_headless(model: &mut Model, ticks: usize, step: f32) {
+    for _ in 0..ticks {
+        model.update(step);
+    }
+}
+
+/// Run a simulation from the command line options and save the final frame to `args.out`, if it was given
+pub fn run(args: &Args) -> Result<(), app::Error> {
+    let mut model = Model::new(&args.config());
+    model.sim_scale = args.sim_scale() as usize;
+
+    if let Some(path) = &args.image {
+        model.load_image(path)?;
+    }
+
+    if let Some(path) = &args.background {
+        model.load_background(path)?;
+    }
+
+    if let Some(pattern) = args.seed {
+        model.seed_pattern(pattern);
+    }
+
+    if let Some(demo) = args.demo {
+        model.seed_pattern(demo.pattern());
+    }
+
+    let mut replay = args.replay.as_deref().map(Replay::load).transpose()?;
+    if let Some(replay) = &replay {
+        replay.check_updates_per_second(args.updates_per_second())?;
+    }
+    // A replay runs for as long as it takes to play back unless it's told otherwise
+    let ticks = args.ticks.unwrap_or_else(|| {
+        replay
+            .as_ref()
+            .map_or(DEFAULT_HEADLESS_TICKS, |replay| replay.ticks())
+    });
+    match replay.as_mut() {
+        Some(replay) => {
+            for tick in 0..ticks {
+                replay.apply(tick, &mut model);
+                model.update(args.step());
+            }
+        }
+        None => run_headless(&mut model, ticks, args.step()),
+    }
+    info!("Ran {} ticks", ticks);
+
+    if let Some(path) = &args.out {
+        model.save_png(path)?;
+        info!("Saved final frame to {}", path.display());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::SimulationConfig;
+    use crate::constants::DEFAULT_MAX_VALUE;
+    use crate::patterns::Demo;
+    use crate::rect::Rect;
+
+    fn seeded_model() -> Model {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(16, 24)));
+        for (index, value) in model.base_matrix.iter_mut().enumerate() {
+            if index % 11 == 0 {
+                *value = DEFAULT_MAX_VALUE;
+            }
+        }
+
+        model
+    }
+
+    #[test]
+    fn test_drop_demo_spreads_symmetrically() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(15, 15)));
+        model.seed_pattern(Demo::Drop.pattern());
+        run_headless(&mut model, 5, Args::default().step());
+
+        let cell = |dx: isize, dy: isize| model.base_matrix[((7 + dx) as usize, (7 + dy) as usize)];
+        assert!(cell(0, 0) < DEFAULT_MAX_VALUE);
+        // Ink spreads at most one cell a tick
+        let radius = model.spread_radius(7, 7, 0.0);
+        assert!((5.0..=50f32.sqrt()).contains(&radius));
+        for &(dx, dy) in &[(1, 0), (0, 1), (1, 1), (2, 1), (3, 3), (5, 0)] {
+            assert!(cell(dx, dy) > 0.0, "ink should reach {{{}, {}}}", dx, dy);
+            for &(other_dx, other_dy) in &[(-dx, -dy), (-dx, dy), (dx, -dy), (dy, dx)] {
+                assert!(
+                    (cell(dx, dy) - cell(other_dx, other_dy)).abs() < 1e-4,
+                    "{{{}, {}}} and {{{}, {}}} should match",
+                    dx,
+                    dy,
+                    other_dx,
+                    other_dy
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_headless_runs_are_deterministic() {
+        let mut first = seeded_model();
+        let mut second = seeded_model();
+        run_headless(&mut first, 100, Args::default().step());
+        run_headless(&mut second, 100, Args::default().step());
+
+        assert_eq!(first.base_matrix.as_slice(), second.base_matrix.as_slice());
+        assert_ne!(
+            seeded_model().base_matrix.as_slice(),
+            first.base_matrix.as_slice()
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use crate::constants::DEFAULT_MAX_UNDO_DEPTH;
+use crate::matrix::Matrix2D;
+use std::collections::VecDeque;
+
+/// Undo and redo stacks of canvas snapshots, one per paint stroke.
+///
+/// Whole matrices are stored rather than diffs of the cells a stroke touched. The simulation keeps changing cells
+/// in between strokes, so by the time a stroke is undone most of the canvas has usually changed anyway.
+pub struct History<T = Matrix2D> {
+    max_depth: usize,
+    redo: Vec<T>,
+    stroke_start: Option<T>,
+    undo: VecDeque<T>,
+}
+
+impl<T: Clone> History<T> {
+    pub fn new() -> Self {
+        Self::with_max_depth(DEFAULT_MAX_UNDO_DEPTH)
+    }
+
+    /// Create a history that holds at most `max_depth` undo snapshots, discarding the oldest when full
+    pub fn with_max_depth(max_depth: usize) -> Self {
+        Self {
+            max_depth: max_depth.max(1),
+            redo: Vec::new(),
+            stroke_start: None,
+            undo: VecDeque::new(),
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Snapshot the canvas before a stroke paints anything. Does nothing if a stroke is already in progress.
+    pub fn begin_stroke(&mut self, canvas: &T) {
+        self.begin_stroke_with(|| canvas.clone());
+    }
+
+    /// Like `begin_stroke`, but the snapshot is only taken by calling `snapshot` if no stroke is in progress
+    pub fn begin_stroke_with(&mut self, snapshot: impl FnOnce() -> T) {
+        if self.stroke_start.is_none() {
+            self.stroke_start = Some(snapshot());
+        }
+    }
+
+    /// Commit the snapshot taken by `begin_stroke` to the undo stack. Any redo history is discarded.
+    pub fn end_stroke(&mut self) {
+        if let Some(snapshot) = self.stroke_start.take() {
+            self.push_undo(snapshot);
+            self.redo.clear();
+        }
+    }
+
+    /// Restore the canvas to how it was before the last stroke. Returns `false` if there is nothing to undo.
+    pub fn undo(&mut self, canvas: &mut T) -> bool {
+        self.undo_with(|snapshot| std::mem::replace(canvas, snapshot))
+    }
+
+    /// Like `undo`, but `restore` puts the snapshot back wherever it belongs and returns what it replaced
+    pub fn undo_with(&mut self, restore: impl FnOnce(T) -> T) -> bool {
+        match self.undo.pop_back() {
+            Some(snapshot) => {
+                self.redo.push(restore(snapshot));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Reapply the last undone stroke. Returns `false` if there is nothing to redo.
+    pub fn redo(&mut self, canvas: &mut T) -> bool {
+        self.redo_with(|snapshot| std::mem::replace(canvas, snapshot))
+    }
+
+    /// Like `redo`, but `restore` puts the snapshot back wherever it belongs and returns what it replaced
+    pub fn redo_with(&mut self, restore: impl FnOnce(T) -> T) -> bool {
+        match self.redo.pop() {
+            Some(snapshot) => {
+                let undone = restore(snapshot);
+                self.push_undo(undone);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn push_undo(&mut self, snapshot: T) {
+        if self.undo.len() >= self.max_depth {
+            let _ = self.undo.pop_front();
+        }
+
+        self.undo.push_back(snapshot);
+    }
+}
+
+impl<T: Clone> Default for History<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn stroke(history: &mut History, canvas: &mut Matrix2D, index: usize) {
+        history.begin_stroke(canvas);
+        *canvas.get_mut(index).unwrap() = 1.0;
+        history.end_stroke();
+    }
+
+    #[test]
+    fn test_undo_restores_prior_state() {
+        let mut history = History::new();
+        let mut canvas = Matrix2D::new(2, 2);
+        stroke(&mut history, &mut canvas, 0);
+        stroke(&mut history, &mut canvas, 3);
+
+        assert!(history.undo(&mut canvas));
+        assert_eq!(
+            vec![1.0, 0.0, 0.0, 0.0],
+            canvas.iter().copied().collect::<Vec<_>>()
+        );
+        assert!(history.undo(&mut canvas));
+        assert_eq!(vec![0.0; 4], canvas.iter().copied().collect::<Vec<_>>());
+        assert!(!history.undo(&mut canvas));
+    }
+
+    #[test]
+    fn test_redo_reapplies_undone_stroke() {
+        let mut history = History::new();
+        let mut canvas = Matrix2D::new(2, 2);
+        stroke(&mut history, &mut canvas, 1);
+
+        assert!(history.undo(&mut canvas));
+        assert!(history.redo(&mut canvas));
+        assert_eq!(
+            vec![0.0, 1.0, 0.0, 0.0],
+            canvas.iter().copied().collect::<Vec<_>>()
+        );
+        assert!(!history.redo(&mut canvas));
+        assert!(history.can_undo());
+    }
+
+    #[test]
+    fn test_new_stroke_discards_redo() {
+        let mut history = History::new();
+        let mut canvas = Matrix2D::new(2, 2);
+        stroke(&mut history, &mut canvas, 1);
+        history.undo(&mut canvas);
+        stroke(&mut history, &mut canvas, 2);
+
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn test_oldest_snapshot_is_discarded_when_full() {
+        let mut history = History::with_max_depth(2);
+        let mut canvas = Matrix2D::new(2, 2);
+        stroke(&mut history, &mut canvas, 0);
+        stroke(&mut history, &mut canvas, 1);
+        stroke(&mut history, &mut canvas, 2);
+
+        assert!(history.undo(&mut canvas));
+        assert!(history.undo(&mut canvas));
+        assert!(!history.undo(&mut canvas));
+        assert_eq!(
+            vec![1.0, 0.0, 0.0, 0.0],
+            canvas.iter().copied().collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,164 @@
+//! Text drawn straight into an RGBA frame with a tiny built-in bitmap font, so the HUD doesn't need a font library
+
+/// Glyphs are 5 pixels wide and 7 pixels high, before they're scaled up
+pub const GLYPH_WIDTH: usize = 5;
+pub const GLYPH_HEIGHT: usize = 7;
+/// How many frame pixels wide each pixel of a glyph is drawn
+pub const SCALE: usize = 2;
+/// The gap between characters and between lines, in glyph pixels
+const SPACING: usize = 1;
+/// The gap between the edge of the HUD's background and its text, in frame pixels
+const MARGIN: usize = 4;
+
+const TEXT_COLOR: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+const BACKGROUND_COLOR: [u8; 4] = [0x00, 0x00, 0x00, 0xff];
+
+/// The rows of a character from top to bottom, where bit 4 of each row is its leftmost pixel. Lowercase letters are
+/// drawn as uppercase, and characters the font doesn't have are left blank.
+fn glyph(character: char) -> [u8; GLYPH_HEIGHT] {
+    match character.to_ascii_uppercase() {
+        '0' => [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e],
+        '1' => [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        '2' => [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f],
+        '3' => [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e],
+        '4' => [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02],
+        '5' => [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e],
+        '6' => [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e],
+        '7' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e],
+        '9' => [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c],
+        'A' => [0x0e, 0x11, 0x11, 0x11, 0x1f, 0x11, 0x11],
+        'B' => [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e],
+        'C' => [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e],
+        'D' => [0x1c, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1c],
+        'E' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f],
+        'F' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10],
+        'G' => [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f],
+        'H' => [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11],
+        'I' => [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f],
+        'M' => [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+        'P' => [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10],
+        'Q' => [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d],
+        'R' => [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11],
+        'S' => [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e],
+        'T' => [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a],
+        'X' => [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0a, 0x04, 0x04, 0x04],
+        'Z' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c],
+        ':' => [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x0c, 0x00],
+        '-' => [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00],
+        _ => [0x00; GLYPH_HEIGHT],
+    }
+}
+
+/// The `(width, height)` in frame pixels of the box `draw_lines` fills for `lines`
+pub fn size(lines: &[String]) -> (usize, usize) {
+    let longest = lines
+        .iter()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0);
+    let text_width = (longest * (GLYPH_WIDTH + SPACING)).saturating_sub(SPACING) * SCALE;
+    let text_height = (lines.len() * (GLYPH_HEIGHT + SPACING)).saturating_sub(SPACING) * SCALE;
+
+    (text_width + MARGIN * 2, text_height + MARGIN * 2)
+}
+
+/// Fill a box in the top-left corner of `frame`, an RGBA frame `frame_width` pixels wide, and write `lines` into it.
+/// Anything that doesn't fit in the frame is cut off.
+pub fn draw_lines(frame: &mut [u8], frame_width: usize, lines: &[String]) {
+    if frame_width == 0 {
+        return;
+    }
+
+    let frame_height = frame.len() / 4 / frame_width;
+    let (box_width, box_height) = size(lines);
+    let mut set_pixel = |x: usize, y: usize, color: [u8; 4]| {
+        if x < frame_width && y < frame_height {
+            let index = (x + y * frame_width) * 4;
+            frame[index..index + 4].copy_from_slice(&color);
+        }
+    };
+
+    for y in 0..box_height {
+        for x in 0..box_width {
+            set_pixel(x, y, BACKGROUND_COLOR);
+        }
+    }
+
+    for (line_index, line) in lines.iter().enumerate() {
+        let top = MARGIN + line_index * (GLYPH_HEIGHT + SPACING) * SCALE;
+        for (character_index, character) in line.chars().enumerate() {
+            let left = MARGIN + character_index * (GLYPH_WIDTH + SPACING) * SCALE;
+            for (row, bits) in glyph(character).iter().enumerate() {
+                for column in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - column)) == 0 {
+                        continue;
+                    }
+
+                    for dy in 0..SCALE {
+                        for dx in 0..SCALE {
+                            set_pixel(
+                                left + column * SCALE + dx,
+                                top + row * SCALE + dy,
+                                TEXT_COLOR,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_size() {
+        let lines = vec!["AB".to_owned(), "C".to_owned()];
+        let (width, height) = size(&lines);
+
+        assert_eq!(MARGIN * 2 + (GLYPH_WIDTH * 2 + SPACING) * SCALE, width);
+        assert_eq!(MARGIN * 2 + (GLYPH_HEIGHT * 2 + SPACING) * SCALE, height);
+    }
+
+    #[test]
+    fn test_draw_lines_writes_text_over_background() {
+        let (width, height) = (40, 30);
+        let mut frame = vec![0x80; width * height * 4];
+        draw_lines(&mut frame, width, &["1".to_owned()]);
+
+        let pixel = |x: usize, y: usize| &frame[(x + y * width) * 4..(x + y * width) * 4 + 4];
+        // The top of a 1 is a single pixel in the middle column, and the corner is background
+        assert_eq!(&TEXT_COLOR, pixel(MARGIN + 2 * SCALE, MARGIN));
+        assert_eq!(&BACKGROUND_COLOR, pixel(MARGIN, MARGIN));
+        assert_eq!(&[0x80; 4], pixel(width - 1, height - 1));
+    }
+
+    #[test]
+    fn test_draw_lines_clips_to_frame() {
+        let mut frame = vec![0; 8 * 4 * 4];
+        draw_lines(&mut frame, 8, &["PALETTE HEAT".to_owned()]);
+
+        assert!(frame
+            .chunks(4)
+            .all(|pixel| pixel == BACKGROUND_COLOR || pixel == TEXT_COLOR));
+    }
+
+    #[test]
+    fn test_unknown_characters_are_blank() {
+        assert_eq!([0; GLYPH_HEIGHT], glyph('~'));
+        assert_eq!(glyph('A'), glyph('a'));
+    }
+}
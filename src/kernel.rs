@@ -0,0 +1,211 @@
+use crate::matrix::Direction;
+use crate::model::NeighbourhoodKind;
+
+/// Relative weights for how a cell's spillover is shared between itself and its eight neighbours. Row 0 is the
+/// northern row, column 0 is the western column, and the center is the part the cell keeps.
+///
+/// Weights don't need to add up to anything in particular. Each tick a cell is divided into as many parts as the
+/// weights add up to, and every neighbour gets as many parts as its weight.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Kernel {
+    weights: [[f32; 3]; 3],
+}
+
+impl Kernel {
+    /// Negative weights are treated as zero. If every weight is zero, the cell keeps all of its ink.
+    pub fn new(weights: [[f32; 3]; 3]) -> Self {
+        let mut weights = weights;
+        for weight in weights.iter_mut().flatten() {
+            *weight = weight.max(0.0);
+        }
+
+        if weights.iter().flatten().all(|weight| *weight == 0.0) {
+            weights[1][1] = 1.0;
+        }
+
+        Self { weights }
+    }
+
+    /// An even split between a cell and all eight of its neighbours
+    pub fn uniform() -> Self {
+        Self::new([[1.0; 3]; 3])
+    }
+
+    /// A uniform kernel that sends `strength` times more ink to the southern row of neighbours
+    pub fn gravity(strength: f32) -> Self {
+        let south = 1.0 + strength.max(0.0);
+        Self::new([[1.0, 1.0, 1.0], [1.0, 1.0, 1.0], [south, south, south]])
+    }
+
+    /// A uniform kernel that sends more ink to the neighbours that lie in the direction of `{dx, dy}`, where positive
+    /// `dx` blows east and positive `dy` blows south
+    pub fn wind(dx: f32, dy: f32) -> Self {
+        let mut weights = [[1.0; 3]; 3];
+        for (row, weights) in weights.iter_mut().enumerate() {
+            for (column, weight) in weights.iter_mut().enumerate() {
+                let (offset_x, offset_y) = (column as f32 - 1.0, row as f32 - 1.0);
+                *weight += (offset_x * dx + offset_y * dy).max(0.0);
+            }
+        }
+
+        Self::new(weights)
+    }
+
+    /// The weight of the part the cell keeps
+    pub fn center(&self) -> f32 {
+        self.weights[1][1]
+    }
+
+    /// The weight of the part sent to the neighbour in `direction`
+    pub fn weight(&self, direction: Direction) -> f32 {
+        let (column, row) = position(direction);
+        self.weights[row][column]
+    }
+
+    /// How many parts a cell is divided into
+    pub fn total(&self) -> f32 {
+        self.weights.iter().flatten().sum()
+    }
+
+    /// The same kernel rescaled so a cell keeps `retention` of its ink, clamped to `[0, 1]`, and shares the rest
+    /// between its neighbours in the same proportions as before. The weights of the rescaled kernel add up to 1. A
+    /// kernel that doesn't send anything to its neighbours has nothing to rescale, so it's returned as it is.
+    pub fn with_retention(&self, retention: f32) -> Self {
+        let retention = retention.clamp(0.0, 1.0);
+        let neighbour_parts = self.total() - self.center();
+        if neighbour_parts <= 0.0 {
+            return *self;
+        }
+
+        let mut rescaled = *self;
+        for weight in rescaled.weights.iter_mut().flatten() {
+            *weight *= (1.0 - retention) / neighbour_parts;
+        }
+        rescaled.weights[1][1] = retention;
+
+        rescaled
+    }
+
+    /// The same kernel for cells that are drawn `aspect` times as wide as they are tall. Ink spreads like a random
+    /// walk, so how far it gets after many ticks goes with the weight of each step times the square of how long the
+    /// step is. Each neighbour's weight is multiplied by the square of how many times shorter it is in cells than on
+    /// screen, so the neighbours to the sides get about `1 / aspect²` of their weight, and ink spreads about as far to
+    /// the sides as it does up and down once it's drawn. An `aspect` that isn't a positive number leaves the kernel as
+    /// it is.
+    pub fn with_aspect(&self, aspect: f32) -> Self {
+        if aspect == 1.0 || aspect <= 0.0 || !aspect.is_finite() {
+            return *self;
+        }
+
+        let mut corrected = *self;
+        for direction in &Direction::ALL {
+            let (dx, dy) = direction.offset();
+            let (dx, dy) = (dx as f32, dy as f32);
+            let cells_squared = dx * dx + dy * dy;
+            let on_screen_squared = (dx * aspect).powi(2) + dy * dy;
+            let (column, row) = position(*direction);
+            corrected.weights[row][column] *= cells_squared / on_screen_squared;
+        }
+
+        corrected
+    }
+
+    /// The same kernel with the weights of any neighbours outside of `neighbourhood` set to zero
+    pub fn masked(&self, neighbourhood: NeighbourhoodKind) -> Self {
+        let mut masked = *self;
+        for direction in &Direction::ALL {
+            if !neighbourhood.includes(*direction) {
+                let (column, row) = position(*direction);
+                masked.weights[row][column] = 0.0;
+            }
+        }
+
+        masked
+    }
+}
+
+/// The `(column, row)` of the weight for the neighbour in `direction`
+fn position(direction: Direction) -> (usize, usize) {
+    let (dx, dy) = direction.offset();
+    ((dx + 1) as usize, (dy + 1) as usize)
+}
+
+impl Default for Kernel {
+    fn default() -> Self {
+        Self::uniform()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_uniform_is_nine_equal_parts() {
+        let kernel = Kernel::uniform();
+        assert_eq!(9.0, kernel.total());
+        assert!(Direction::ALL
+            .iter()
+            .all(|direction| kernel.weight(*direction) == kernel.center()));
+    }
+
+    #[test]
+    fn test_gravity_favours_south() {
+        let kernel = Kernel::gravity(2.0);
+        assert_eq!(3.0, kernel.weight(Direction::South));
+        assert_eq!(3.0, kernel.weight(Direction::SouthWest));
+        assert_eq!(1.0, kernel.weight(Direction::North));
+    }
+
+    #[test]
+    fn test_wind_favours_its_direction() {
+        let kernel = Kernel::wind(1.0, 0.0);
+        assert_eq!(2.0, kernel.weight(Direction::East));
+        assert_eq!(2.0, kernel.weight(Direction::NorthEast));
+        assert_eq!(1.0, kernel.weight(Direction::North));
+        assert_eq!(1.0, kernel.weight(Direction::West));
+    }
+
+    #[test]
+    fn test_von_neumann_mask_drops_diagonals() {
+        let kernel = Kernel::uniform().masked(NeighbourhoodKind::VonNeumann);
+        assert_eq!(5.0, kernel.total());
+        assert_eq!(0.0, kernel.weight(Direction::NorthEast));
+        assert_eq!(1.0, kernel.weight(Direction::East));
+    }
+
+    #[test]
+    fn test_with_retention() {
+        let kernel = Kernel::gravity(1.0).with_retention(0.25);
+
+        assert_eq!(0.25, kernel.center());
+        assert!((kernel.total() - 1.0).abs() < 1e-6);
+        assert_eq!(
+            2.0 * kernel.weight(Direction::North),
+            kernel.weight(Direction::South)
+        );
+        assert_eq!(0.75 / 11.0, kernel.weight(Direction::North));
+    }
+
+    #[test]
+    fn test_with_aspect_scales_horizontal_weights() {
+        let kernel = Kernel::uniform().with_aspect(2.0);
+
+        assert_eq!(0.25, kernel.weight(Direction::East));
+        assert_eq!(0.25, kernel.weight(Direction::West));
+        assert_eq!(1.0, kernel.weight(Direction::North));
+        assert_eq!(1.0, kernel.weight(Direction::South));
+        assert_eq!(1.0, kernel.center());
+        assert_eq!(0.4, kernel.weight(Direction::NorthEast));
+
+        assert_eq!(Kernel::uniform(), Kernel::uniform().with_aspect(1.0));
+        assert_eq!(Kernel::uniform(), Kernel::uniform().with_aspect(0.0));
+    }
+
+    #[test]
+    fn test_all_zero_weights_keep_everything() {
+        let kernel = Kernel::new([[0.0, -1.0, 0.0], [0.0, 0.0, 0.0], [0.0, 0.0, 0.0]]);
+        assert_eq!(1.0, kernel.total());
+        assert_eq!(1.0, kernel.center());
+    }
+}
@@ -0,0 +1,19 @@
+pub mod app;
+pub mod cli;
+pub mod config;
+pub mod constants;
+pub mod decay;
+pub mod headless;
+pub mod history;
+pub mod hud;
+pub mod kernel;
+pub mod matrix;
+pub mod matrix_rgb;
+pub mod model;
+pub mod palette;
+pub mod patterns;
+pub mod recorder;
+pub mod rect;
+pub mod replay;
+pub mod symmetry;
+pub mod vector2;
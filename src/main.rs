@@ -1,15 +1,30 @@
-mod app;
-mod constants;
-mod matrix;
-mod model;
-mod rect;
-mod vector2;
-
-use app::{run, App};
+use diffuser::{
+    app::{run, App},
+    cli::Args,
+    headless,
+};
+use log::error;
 
 fn main() {
     env_logger::init();
 
-    let app = App::new();
+    let mut args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    args.apply_env_overrides(|name| std::env::var(name).ok());
+
+    if args.headless {
+        if let Err(e) = headless::run(&args) {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let app = App::new(&args);
     run(app);
 }
@@ -1,27 +1,76 @@
+use crate::app;
+use crate::constants::DEFAULT_MAX_VALUE;
+use crate::vector2::Vector2;
 use log::warn;
+use rayon::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{self, Write};
+use std::ops::{AddAssign, Index, IndexMut, SubAssign};
+use std::path::Path;
+use std::str::FromStr;
 
-pub struct Matrix2D {
-    cells: Vec<f32>,
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "SerializedMatrix2D<T>")
+)]
+pub struct Matrix2D<T = f32> {
+    boundary_mode: BoundaryMode,
+    cells: Vec<T>,
     width: usize,
     height: usize,
 }
 
-impl Matrix2D {
+/// The matrix the simulation runs on
+pub type Matrix2Df = Matrix2D<f32>;
+
+impl<T: Copy + Default> Matrix2D<T> {
     pub fn new(height: usize, width: usize) -> Self {
+        Self::with_boundary(height, width, BoundaryMode::default())
+    }
+
+    pub fn with_boundary(height: usize, width: usize, boundary_mode: BoundaryMode) -> Self {
         if height > width {
             warn!("Matrix2D height ({}) is greater than Matrix2D width ({}). Are you sure about that?", height, width)
         }
 
         let length = height * width;
-        let cells = (0..length).into_iter().map(|_| 0.0).collect();
+        let cells = (0..length).map(|_| T::default()).collect();
 
         Self {
+            boundary_mode,
             cells,
             height,
             width,
         }
     }
 
+    /// Build a matrix from cells laid out row by row. `cells` must hold exactly `height * width` values.
+    pub fn from_vec(height: usize, width: usize, cells: Vec<T>) -> Result<Self, app::Error> {
+        if cells.len() != height * width {
+            return Err(app::Error::InvalidMatrixLength {
+                len: cells.len(),
+                width,
+                height,
+            });
+        }
+
+        Ok(Self {
+            boundary_mode: BoundaryMode::default(),
+            cells,
+            height,
+            width,
+        })
+    }
+
+    pub fn boundary_mode(&self) -> BoundaryMode {
+        self.boundary_mode
+    }
+
     pub fn h(&self) -> usize {
         self.height
     }
@@ -34,30 +83,252 @@ impl Matrix2D {
         self.cells.len()
     }
 
-    pub fn get(&self, index: usize) -> Option<&f32> {
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.cells.get(index)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.cells.get_mut(index)
+    }
+
+    /// Write `value` to the cell at `{x, y}`, or fail without writing anything if it's off the matrix
+    pub fn set(&mut self, x: usize, y: usize, value: T) -> Result<(), app::Error> {
+        let index = self.index_from_xy(x, y)?;
+        self.cells[index] = value;
+
+        Ok(())
+    }
+
+    /// The cell at `p`, or `None` if it's off the matrix
+    pub fn get_v(&self, p: Vector2<usize>) -> Option<&T> {
+        let index = self.index_from_xy(p.x, p.y).ok()?;
         self.cells.get(index)
     }
 
-    pub fn get_mut(&mut self, index: usize) -> Option<&mut f32> {
+    /// The cell at `p`, or `None` if it's off the matrix
+    pub fn get_mut_v(&mut self, p: Vector2<usize>) -> Option<&mut T> {
+        let index = self.index_from_xy(p.x, p.y).ok()?;
         self.cells.get_mut(index)
     }
 
-    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut f32> {
+    /// Every cell, row by row
+    pub fn as_slice(&self) -> &[T] {
+        &self.cells
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.cells
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.cells.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
         self.cells.iter_mut()
     }
 
+    /// Every cell along with its `x` and `y`, row by row
+    pub fn iter_xy(&self) -> impl Iterator<Item = (usize, usize, &T)> {
+        let width = self.width;
+        self.cells.iter().enumerate().map(move |(index, cell)| {
+            let (x, y) = calculate_xy_from_index(index, width);
+            (x, y, cell)
+        })
+    }
+
+    /// Every cell along with its `x` and `y`, row by row
+    pub fn iter_xy_mut(&mut self) -> impl Iterator<Item = (usize, usize, &mut T)> {
+        let width = self.width;
+        self.cells.iter_mut().enumerate().map(move |(index, cell)| {
+            let (x, y) = calculate_xy_from_index(index, width);
+            (x, y, cell)
+        })
+    }
+
+    /// The cells of row `y`, or `None` if there's no such row. Rows are stored next to each other so this is O(1).
+    pub fn row(&self, y: usize) -> Option<&[T]> {
+        if y >= self.height {
+            return None;
+        }
+
+        let start = y * self.width;
+        self.cells.get(start..start + self.width)
+    }
+
+    /// Every row from top to bottom
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        // chunks can't be 0 long, but an empty matrix has no rows either way
+        self.cells.chunks(self.width.max(1))
+    }
+
+    /// The cells of column `x` from top to bottom, or nothing if there's no such column. Columns aren't stored next to
+    /// each other, so this strides through the matrix one row at a time.
+    pub fn col(&self, x: usize) -> impl Iterator<Item = &T> {
+        let height = if x < self.width { self.height } else { 0 };
+        self.cells
+            .iter()
+            .skip(x)
+            .step_by(self.width.max(1))
+            .take(height)
+    }
+
+    /// Every column from left to right
+    pub fn cols(&self) -> impl Iterator<Item = impl Iterator<Item = &T>> {
+        (0..self.width).map(move |x| self.col(x))
+    }
+
+    /// Change the dimensions of the matrix. The overlapping top-left region keeps its values and any newly added
+    /// cells are set to `T::default()`, which is zero for numbers.
+    pub fn resize(&mut self, new_height: usize, new_width: usize) {
+        let mut cells = vec![T::default(); new_height * new_width];
+        let copy_width = self.width.min(new_width);
+
+        for y in 0..self.height.min(new_height) {
+            let src = calculate_index_from_xy(0, y, self.width);
+            let dst = calculate_index_from_xy(0, y, new_width);
+            cells[dst..dst + copy_width].copy_from_slice(&self.cells[src..src + copy_width]);
+        }
+
+        self.cells = cells;
+        self.height = new_height;
+        self.width = new_width;
+    }
+
+    /// Set every cell to `value` in place. The matrix keeps its dimensions and its allocation.
+    pub fn fill(&mut self, value: T) {
+        for cell in self.cells.iter_mut() {
+            *cell = value;
+        }
+    }
+
+    /// Set every cell to zero, wiping the matrix clean.
+    pub fn clear(&mut self) {
+        self.fill(T::default());
+    }
+
+    /// A new matrix of the same dimensions and boundary mode, with `f` applied to every cell
+    pub fn map(&self, f: impl Fn(T) -> T) -> Self {
+        Self {
+            boundary_mode: self.boundary_mode,
+            cells: self.cells.iter().map(|cell| f(*cell)).collect(),
+            height: self.height,
+            width: self.width,
+        }
+    }
+
+    /// Apply `f` to every cell
+    pub fn map_in_place(&mut self, f: impl Fn(T) -> T) {
+        for cell in self.cells.iter_mut() {
+            *cell = f(*cell);
+        }
+    }
+
+    /// A new matrix with rows and columns swapped, so the cell at `{x, y}` ends up at `{y, x}` and the width and height
+    /// trade places
+    pub fn transpose(&self) -> Self {
+        let cells = (0..self.width)
+            .flat_map(|x| (0..self.height).map(move |y| (x, y)))
+            .map(|(x, y)| self.cells[calculate_index_from_xy(x, y, self.width)])
+            .collect();
+
+        Self {
+            boundary_mode: self.boundary_mode,
+            cells,
+            height: self.width,
+            width: self.height,
+        }
+    }
+
+    /// Mirror the matrix left to right in place
+    pub fn flip_horizontal(&mut self) {
+        if self.width == 0 {
+            return;
+        }
+
+        for row in self.cells.chunks_mut(self.width) {
+            row.reverse();
+        }
+    }
+
+    /// Mirror the matrix top to bottom in place
+    pub fn flip_vertical(&mut self) {
+        for y in 0..self.height / 2 {
+            let mirrored_y = self.height - 1 - y;
+            for x in 0..self.width {
+                self.cells.swap(
+                    calculate_index_from_xy(x, y, self.width),
+                    calculate_index_from_xy(x, mirrored_y, self.width),
+                );
+            }
+        }
+    }
+
+    fn index_from_xy(&self, x: usize, y: usize) -> Result<usize, app::Error> {
+        if x < self.width && y < self.height {
+            Ok(calculate_index_from_xy(x, y, self.width))
+        } else {
+            Err(app::Error::InvalidXyIndex {
+                x,
+                y,
+                width: self.width,
+                height: self.height,
+            })
+        }
+    }
+
+    pub fn get_neighbouring_cell(&self, index: usize, direction: Direction) -> Option<&T> {
+        match self.get_neighbour_index(index, direction) {
+            Some(neighbour_index) => self.cells.get(neighbour_index),
+            _ => None,
+        }
+    }
+
     pub fn get_neighbouring_cell_mut(
         &mut self,
         index: usize,
         direction: Direction,
-    ) -> Option<&mut f32> {
+    ) -> Option<&mut T> {
         match self.get_neighbour_index(index, direction) {
             Some(neighbour_index) => self.cells.get_mut(neighbour_index),
             _ => None,
         }
     }
 
+    /// The neighbour of the cell at `index` in `direction` as `(neighbour_index, x, y)`, or `None` if there isn't one
+    pub fn neighbour(&self, index: usize, direction: Direction) -> Option<(usize, usize, usize)> {
+        self.get_neighbour_index(index, direction)
+            .filter(|neighbour_index| *neighbour_index < self.cells.len())
+            .map(|neighbour_index| {
+                let (x, y) = calculate_xy_from_index(neighbour_index, self.width);
+                (neighbour_index, x, y)
+            })
+    }
+
+    /// Every neighbour of the cell at `index` that exists, in the order of `Direction::ALL`
+    pub fn neighbours(&self, index: usize) -> impl Iterator<Item = (Direction, &T)> {
+        self.neighbour_indices(index)
+            .map(move |(direction, neighbour_index)| (direction, &self.cells[neighbour_index]))
+    }
+
+    /// Like `neighbours` but yields the index of each neighbour instead of its value. Look cells up with `get_mut` to
+    /// change them, handing out mutable references to all eight at once isn't possible.
+    pub fn neighbour_indices(&self, index: usize) -> impl Iterator<Item = (Direction, usize)> + '_ {
+        Direction::ALL.iter().filter_map(move |direction| {
+            self.get_neighbour_index(index, *direction)
+                .map(|neighbour_index| (*direction, neighbour_index))
+        })
+    }
+
     fn get_neighbour_index(&self, index: usize, direction: Direction) -> Option<usize> {
+        if let BoundaryMode::Wrap = self.boundary_mode {
+            return self.get_wrapped_neighbour_index(index, direction);
+        }
+
         let index = index as isize;
         let width = self.width as isize;
         let height = self.height as isize;
@@ -74,19 +345,316 @@ impl Matrix2D {
             SouthWest => index_to_the_southwest(index, height, width),
         };
 
-        if neighbour_index < 0 || neighbour_index > self.cells.len() as isize {
+        if neighbour_index < 0 {
             None
         } else {
             Some(neighbour_index as usize)
         }
     }
+
+    fn get_wrapped_neighbour_index(&self, index: usize, direction: Direction) -> Option<usize> {
+        if index >= self.cells.len() {
+            return None;
+        }
+
+        let (dx, dy) = direction.offset();
+        let (x, y) = calculate_xy_from_index(index, self.width);
+        let x = (x as isize + dx).rem_euclid(self.width as isize) as usize;
+        let y = (y as isize + dy).rem_euclid(self.height as isize) as usize;
+
+        Some(calculate_index_from_xy(x, y, self.width))
+    }
+}
+
+impl<T: Copy + Default + Send + Sync> Matrix2D<T> {
+    pub fn par_iter(&self) -> rayon::slice::Iter<'_, T> {
+        self.cells.par_iter()
+    }
+
+    pub fn par_iter_mut(&mut self) -> rayon::slice::IterMut<'_, T> {
+        self.cells.par_iter_mut()
+    }
+
+    /// Apply `f` to every cell like `map_in_place`, spread across threads
+    pub fn par_map_in_place(&mut self, f: impl Fn(T) -> T + Send + Sync) {
+        self.cells.par_iter_mut().for_each(|cell| *cell = f(*cell));
+    }
+}
+
+impl Matrix2D<f32> {
+    /// Load an image at its native size. Pixels are converted to luminance and mapped so that black becomes
+    /// `DEFAULT_MAX_VALUE` and white becomes `0.0`, the same way ink is drawn.
+    pub fn from_image(path: &Path) -> Result<Self, app::Error> {
+        let image = image::open(path)?.into_luma8();
+        let (width, height) = image.dimensions();
+        let mut matrix = Self::new(height as usize, width as usize);
+
+        for (cell, luma) in matrix.cells.iter_mut().zip(image.pixels()) {
+            *cell = (255.0 - luma.0[0] as f32) / 255.0 * DEFAULT_MAX_VALUE;
+        }
+
+        Ok(matrix)
+    }
+
+    /// Read a grayscale matrix back out of an RGBA frame `width` pixels wide and `height` high, undoing how `draw`
+    /// maps cells to pixels with the grayscale palette, so white becomes `0.0` and black becomes `1.0`. Only the red
+    /// channel is read. Fails if the frame isn't exactly `4 * width * height` bytes long.
+    pub fn from_rgba(frame: &[u8], width: usize, height: usize) -> Result<Self, app::Error> {
+        if frame.len() != 4 * width * height {
+            return Err(app::Error::InvalidFrameLength {
+                len: frame.len(),
+                width,
+                height,
+            });
+        }
+
+        let cells = frame
+            .chunks_exact(4)
+            .map(|pixel| (255 - pixel[0]) as f32 / 255.0)
+            .collect();
+        Self::from_vec(height, width, cells)
+    }
+
+    /// Whether both matrices are the same size and every pair of cells is within `epsilon` of each other
+    pub fn approx_eq(&self, other: &Matrix2D, epsilon: f32) -> bool {
+        self.width == other.width
+            && self.height == other.height
+            && self
+                .cells
+                .iter()
+                .zip(other.cells.iter())
+                .all(|(a, b)| (a - b).abs() <= epsilon)
+    }
+
+    /// Multiply every cell by `factor`
+    pub fn mul_scalar(&mut self, factor: f32) {
+        self.par_map_in_place(|value| value * factor);
+    }
+
+    /// The total of every cell
+    pub fn sum(&self) -> f32 {
+        self.cells.iter().sum()
+    }
+
+    /// The average value of a cell, or `0.0` if the matrix is empty
+    pub fn mean(&self) -> f32 {
+        if self.cells.is_empty() {
+            0.0
+        } else {
+            self.sum() / self.cells.len() as f32
+        }
+    }
+
+    /// The largest value of any cell, or `None` if the matrix is empty
+    pub fn max(&self) -> Option<f32> {
+        self.cells.iter().copied().reduce(f32::max)
+    }
+
+    /// The smallest value of any cell, or `None` if the matrix is empty
+    pub fn min(&self) -> Option<f32> {
+        self.cells.iter().copied().reduce(f32::min)
+    }
+
+    /// How many cells hold something other than zero
+    pub fn count_nonzero(&self) -> usize {
+        self.count_where(|value| value != 0.0)
+    }
+
+    /// How many cells `pred` returns `true` for
+    pub fn count_where(&self, pred: impl Fn(f32) -> bool) -> usize {
+        self.cells.iter().filter(|value| pred(**value)).count()
+    }
+
+    /// Rescale every cell linearly so the smallest becomes `0.0` and the largest `1.0`. Does nothing if every cell
+    /// holds the same value, or the matrix is empty.
+    pub fn normalize(&mut self) {
+        let (min, max) = match (self.min(), self.max()) {
+            (Some(min), Some(max)) if max > min => (min, max),
+            _ => return,
+        };
+
+        let range = max - min;
+        self.par_map_in_place(|value| (value - min) / range);
+    }
+
+    /// Stamp `src` onto this matrix with its top-left corner at `{dst_x, dst_y}`, combining each pair of cells with
+    /// `blend`. Whatever part of `src` falls off the matrix is clipped.
+    pub fn blit(&mut self, src: &Matrix2D, dst_x: usize, dst_y: usize, blend: BlendMode) {
+        let columns = src.width.min(self.width.saturating_sub(dst_x));
+        if columns == 0 {
+            return;
+        }
+
+        for (src_row, y) in src.rows().zip(dst_y..self.height) {
+            let start = calculate_index_from_xy(dst_x, y, self.width);
+            let dst_row = &mut self.cells[start..start + columns];
+            for (dst, src) in dst_row.iter_mut().zip(src_row) {
+                *dst = blend.apply(*dst, *src);
+            }
+        }
+    }
+
+    /// Write the matrix as CSV, one line per row with every value rounded to 3 decimal places
+    pub fn write_csv(&self, mut w: impl Write) -> io::Result<()> {
+        for row in self.rows() {
+            let line: Vec<String> = row.iter().map(|value| format!("{:.3}", value)).collect();
+            writeln!(w, "{}", line.join(","))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The shape of a serialized `Matrix2D`, which is checked for consistency before it's turned into a real one
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct SerializedMatrix2D<T> {
+    #[serde(default)]
+    boundary_mode: BoundaryMode,
+    cells: Vec<T>,
+    width: usize,
+    height: usize,
+}
+
+/// `(frame, width, height)`, see `Matrix2D::from_rgba`
+impl TryFrom<(&[u8], usize, usize)> for Matrix2D<f32> {
+    type Error = app::Error;
+
+    fn try_from((frame, width, height): (&[u8], usize, usize)) -> Result<Self, Self::Error> {
+        Self::from_rgba(frame, width, height)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T> TryFrom<SerializedMatrix2D<T>> for Matrix2D<T> {
+    type Error = app::Error;
+
+    fn try_from(serialized: SerializedMatrix2D<T>) -> Result<Self, Self::Error> {
+        let SerializedMatrix2D {
+            boundary_mode,
+            cells,
+            width,
+            height,
+        } = serialized;
+
+        if cells.len() != width * height {
+            return Err(app::Error::InvalidMatrixLength {
+                len: cells.len(),
+                width,
+                height,
+            });
+        }
+
+        Ok(Self {
+            boundary_mode,
+            cells,
+            width,
+            height,
+        })
+    }
+}
+
+impl<T: Copy + Default> Index<(usize, usize)> for Matrix2D<T> {
+    type Output = T;
+
+    fn index(&self, (x, y): (usize, usize)) -> &T {
+        match self.index_from_xy(x, y) {
+            Ok(index) => &self.cells[index],
+            Err(e) => panic!("{}", e),
+        }
+    }
+}
+
+impl<T: Copy + Default> IndexMut<(usize, usize)> for Matrix2D<T> {
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut T {
+        match self.index_from_xy(x, y) {
+            Ok(index) => &mut self.cells[index],
+            Err(e) => panic!("{}", e),
+        }
+    }
+}
+
+/// Matrices are equal when they're the same size and hold the same cells, however their edges behave
+impl<T: PartialEq> PartialEq for Matrix2D<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.width == other.width && self.height == other.height && self.cells == other.cells
+    }
+}
+
+/// Panics unless `a` and `b` are the same size, the same way indexing out of bounds does
+fn assert_same_size<T>(a: &Matrix2D<T>, b: &Matrix2D<T>) {
+    assert!(
+        a.width == b.width && a.height == b.height,
+        "Can't combine a {}x{} matrix with a {}x{} one",
+        a.width,
+        a.height,
+        b.width,
+        b.height
+    );
+}
+
+/// Add `rhs` to this matrix cell by cell. Panics if they aren't the same size.
+impl<T: Copy + Default + AddAssign> AddAssign<&Matrix2D<T>> for Matrix2D<T> {
+    fn add_assign(&mut self, rhs: &Matrix2D<T>) {
+        assert_same_size(self, rhs);
+        for (value, other) in self.cells.iter_mut().zip(rhs.cells.iter()) {
+            *value += *other;
+        }
+    }
+}
+
+/// Subtract `rhs` from this matrix cell by cell. Panics if they aren't the same size.
+impl<T: Copy + Default + SubAssign> SubAssign<&Matrix2D<T>> for Matrix2D<T> {
+    fn sub_assign(&mut self, rhs: &Matrix2D<T>) {
+        assert_same_size(self, rhs);
+        for (value, other) in self.cells.iter_mut().zip(rhs.cells.iter()) {
+            *value -= *other;
+        }
+    }
+}
+
+/// How neighbour lookups behave at the edges of a `Matrix2D`
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum BoundaryMode {
+    /// Cells on an edge have no neighbours beyond it
+    #[default]
+    Clamp,
+    /// The grid is treated as a torus, so the right edge neighbours the left edge and the top neighbours the bottom
+    Wrap,
+}
+
+/// How `Matrix2D::blit` combines a stamped cell with the cell underneath it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlendMode {
+    /// The stamped cell takes the place of the cell underneath
+    Replace,
+    /// The stamped cell is added to the cell underneath
+    Add,
+    /// Whichever of the two is larger is kept
+    Max,
+}
+
+impl BlendMode {
+    pub fn apply(self, dst: f32, src: f32) -> f32 {
+        match self {
+            BlendMode::Replace => src,
+            BlendMode::Add => dst + src,
+            BlendMode::Max => dst.max(src),
+        }
+    }
 }
 
 pub fn calculate_index_from_xy(x: usize, y: usize, width: usize) -> usize {
     x + width * y
 }
 
-#[derive(Clone, Copy)]
+/// The inverse of `calculate_index_from_xy`, returns `(x, y)`
+pub fn calculate_xy_from_index(index: usize, width: usize) -> (usize, usize) {
+    (index % width, index / width)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Direction {
     NorthWest,
     North,
@@ -98,96 +666,147 @@ pub enum Direction {
     SouthWest,
 }
 
-fn index_is_in_range(index: isize, height: isize, width: isize) -> bool {
-    index >= 0 && index < (height * width)
+impl Direction {
+    /// Every direction, ordered by the index of the neighbour it points to. Summing neighbours in this order gives the
+    /// same result as a serial scatter that visits cells by index.
+    pub const ALL: [Direction; 8] = [
+        Direction::NorthWest,
+        Direction::North,
+        Direction::NorthEast,
+        Direction::West,
+        Direction::East,
+        Direction::SouthWest,
+        Direction::South,
+        Direction::SouthEast,
+    ];
+
+    /// The direction pointing the other way
+    pub fn opposite(self) -> Self {
+        use Direction::*;
+        match self {
+            NorthWest => SouthEast,
+            North => South,
+            NorthEast => SouthWest,
+            West => East,
+            East => West,
+            SouthEast => NorthWest,
+            South => North,
+            SouthWest => NorthEast,
+        }
+    }
+
+    /// How far a step in this direction moves along each axis, as `(dx, dy)`. North is up, towards smaller `y`.
+    pub fn offset(self) -> (isize, isize) {
+        use Direction::*;
+        match self {
+            NorthWest => (-1, -1),
+            North => (0, -1),
+            NorthEast => (1, -1),
+            West => (-1, 0),
+            East => (1, 0),
+            SouthEast => (1, 1),
+            South => (0, 1),
+            SouthWest => (-1, 1),
+        }
+    }
 }
 
-fn index_is_in_first_row(index: isize, _height: isize, width: isize) -> bool {
-    index >= 0 && index < width
+/// Written out in full in lowercase, like `northeast`, which `FromStr` reads back
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Direction::*;
+        let name = match self {
+            NorthWest => "northwest",
+            North => "north",
+            NorthEast => "northeast",
+            West => "west",
+            East => "east",
+            SouthEast => "southeast",
+            South => "south",
+            SouthWest => "southwest",
+        };
+
+        f.write_str(name)
+    }
 }
 
-fn index_is_in_last_row(index: isize, height: isize, width: isize) -> bool {
-    index >= (width * (height - 1)) && index < (width * height)
+/// Reads compass abbreviations like `NE` and full names like `northeast` or `north-east`, ignoring case
+impl FromStr for Direction {
+    type Err = app::Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        use Direction::*;
+        let normalized: String = name
+            .chars()
+            .filter(|c| !matches!(c, '-' | '_' | ' '))
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+
+        match normalized.as_str() {
+            "nw" | "northwest" => Ok(NorthWest),
+            "n" | "north" => Ok(North),
+            "ne" | "northeast" => Ok(NorthEast),
+            "w" | "west" => Ok(West),
+            "e" | "east" => Ok(East),
+            "se" | "southeast" => Ok(SouthEast),
+            "s" | "south" => Ok(South),
+            "sw" | "southwest" => Ok(SouthWest),
+            _ => Err(app::Error::UnknownDirection {
+                name: name.to_owned(),
+            }),
+        }
+    }
 }
 
-fn index_is_in_first_column(index: isize, height: isize, width: isize) -> bool {
-    index_is_in_range(index, height, width) && index % width == 0
+fn index_is_in_range(index: isize, height: isize, width: isize) -> bool {
+    index >= 0 && index < (height * width)
 }
 
-fn index_is_in_last_column(index: isize, height: isize, width: isize) -> bool {
-    index_is_in_range(index, height, width) && (index + 1) % width == 0
+/// The index of the cell one step from `index` in `direction`, or `-1` if either cell is outside of the matrix
+fn index_in_direction(index: isize, height: isize, width: isize, direction: Direction) -> isize {
+    if !index_is_in_range(index, height, width) {
+        return -1;
+    }
+
+    let (dx, dy) = direction.offset();
+    let (x, y) = (index % width + dx, index / width + dy);
+    if x < 0 || x >= width || y < 0 || y >= height {
+        -1
+    } else {
+        y * width + x
+    }
 }
 
 fn index_to_the_northwest(index: isize, height: isize, width: isize) -> isize {
-    match (
-        index_is_in_first_column(index, height, width),
-        index_to_the_north(index, height, width),
-    ) {
-        (false, north_index) if north_index != -1 => north_index - 1,
-        _ => -1,
-    }
+    index_in_direction(index, height, width, Direction::NorthWest)
 }
 
 fn index_to_the_north(index: isize, height: isize, width: isize) -> isize {
-    if index_is_in_range(index, height, width) && !index_is_in_first_row(index, height, width) {
-        index - width
-    } else {
-        -1
-    }
+    index_in_direction(index, height, width, Direction::North)
 }
 
 fn index_to_the_northeast(index: isize, height: isize, width: isize) -> isize {
-    match (
-        index_is_in_last_column(index, height, width),
-        index_to_the_north(index, height, width),
-    ) {
-        (false, north_index) if north_index != -1 => north_index + 1,
-        _ => -1,
-    }
+    index_in_direction(index, height, width, Direction::NorthEast)
 }
 
 fn index_to_the_west(index: isize, height: isize, width: isize) -> isize {
-    if !index_is_in_first_column(index, height, width) {
-        index - 1
-    } else {
-        -1
-    }
+    index_in_direction(index, height, width, Direction::West)
 }
 
 fn index_to_the_east(index: isize, height: isize, width: isize) -> isize {
-    if !index_is_in_last_column(index, height, width) {
-        index + 1
-    } else {
-        -1
-    }
+    index_in_direction(index, height, width, Direction::East)
 }
 
 fn index_to_the_southeast(index: isize, height: isize, width: isize) -> isize {
-    match (
-        index_is_in_last_column(index, height, width),
-        index_to_the_south(index, height, width),
-    ) {
-        (false, north_index) if north_index != -1 => north_index + 1,
-        _ => -1,
-    }
+    index_in_direction(index, height, width, Direction::SouthEast)
 }
 
 fn index_to_the_south(index: isize, height: isize, width: isize) -> isize {
-    if index_is_in_range(index, height, width) && !index_is_in_last_row(index, height, width) {
-        index + width
-    } else {
-        -1
-    }
+    index_in_direction(index, height, width, Direction::South)
 }
 
 fn index_to_the_southwest(index: isize, height: isize, width: isize) -> isize {
-    match (
-        index_is_in_first_column(index, height, width),
-        index_to_the_south(index, height, width),
-    ) {
-        (false, north_index) if north_index != -1 => north_index - 1,
-        _ => -1,
-    }
+    index_in_direction(index, height, width, Direction::SouthWest)
 }
 
 #[cfg(test)]
@@ -202,6 +821,563 @@ mod test {
     2 |12 13 14 15 16 17
     */
 
+    #[test]
+    fn test_fill() {
+        let mut matrix = Matrix2D::new(3, 6);
+        matrix.fill(0.7);
+
+        assert_eq!(18, matrix.len());
+        assert_eq!(3, matrix.h());
+        assert_eq!(6, matrix.w());
+        for index in 0..matrix.len() {
+            assert_eq!(Some(&0.7), matrix.get(index));
+        }
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut matrix = Matrix2D::new(3, 6);
+        matrix.fill(0.7);
+        matrix.clear();
+
+        assert_eq!(18, matrix.len());
+        for index in 0..matrix.len() {
+            assert_eq!(Some(&0.0), matrix.get(index));
+        }
+    }
+
+    #[test]
+    fn test_clone_is_equal_until_mutated() {
+        let mut matrix = Matrix2D::from_vec(2, 2, vec![1.0, 2.0, 3.0, 4.0]).unwrap();
+        let clone = matrix.clone();
+        assert_eq!(matrix, clone);
+
+        matrix[(1, 1)] = 5.0;
+        assert_ne!(matrix, clone);
+        assert_eq!(4.0, clone[(1, 1)]);
+        assert_ne!(Matrix2Df::new(2, 3), Matrix2Df::new(3, 2));
+    }
+
+    #[test]
+    fn test_approx_eq() {
+        let matrix = Matrix2D::from_vec(1, 3, vec![0.1, 0.2, 0.3]).unwrap();
+        let nudged = matrix.map(|value| value + 1e-6);
+
+        assert_ne!(matrix, nudged);
+        assert!(matrix.approx_eq(&nudged, 1e-5));
+        assert!(!matrix.approx_eq(&nudged, 1e-7));
+        assert!(!Matrix2Df::new(1, 3).approx_eq(&Matrix2Df::new(3, 1), 1.0));
+    }
+
+    #[test]
+    fn test_transpose() {
+        let matrix = Matrix2D::from_vec(2, 3, vec![0, 1, 2, 3, 4, 5]).unwrap();
+        let transposed = matrix.transpose();
+
+        assert_eq!(3, transposed.h());
+        assert_eq!(2, transposed.w());
+        assert_eq!(&[0, 3, 1, 4, 2, 5][..], transposed.as_slice());
+        for (x, y, value) in matrix.iter_xy() {
+            assert_eq!(*value, transposed[(y, x)]);
+        }
+        assert_eq!(matrix, transposed.transpose());
+    }
+
+    #[test]
+    fn test_flips() {
+        let matrix = Matrix2D::from_vec(3, 2, vec![0, 1, 2, 3, 4, 5]).unwrap();
+
+        let mut flipped = matrix.clone();
+        flipped.flip_horizontal();
+        assert_eq!(&[1, 0, 3, 2, 5, 4][..], flipped.as_slice());
+        flipped.flip_horizontal();
+        assert_eq!(matrix, flipped);
+
+        flipped.flip_vertical();
+        assert_eq!(&[4, 5, 2, 3, 0, 1][..], flipped.as_slice());
+        flipped.flip_vertical();
+        assert_eq!(matrix, flipped);
+
+        Matrix2D::<f32>::new(0, 0).flip_horizontal();
+    }
+
+    #[test]
+    fn test_par_map_in_place_matches_map_in_place() {
+        let cells = (0..300 * 200)
+            .map(|index| (index % 97) as f32 / 7.0)
+            .collect();
+        let mut serial = Matrix2D::from_vec(200, 300, cells).unwrap();
+        let mut parallel = serial.clone();
+        let f = |value: f32| (value * 1.5 - 2.0).max(0.0).sqrt();
+
+        serial.map_in_place(f);
+        parallel.par_map_in_place(f);
+        assert_eq!(serial, parallel);
+    }
+
+    #[test]
+    fn test_map() {
+        let mut matrix = Matrix2D::with_boundary(3, 6, BoundaryMode::Wrap);
+        matrix[(2, 1)] = 0.5;
+        let mapped = matrix.map(|value| value * 2.0 + 1.0);
+
+        assert_eq!(3, mapped.h());
+        assert_eq!(6, mapped.w());
+        assert_eq!(BoundaryMode::Wrap, mapped.boundary_mode());
+        assert_eq!(2.0, mapped[(2, 1)]);
+        assert_eq!(1.0, mapped[(0, 0)]);
+        assert_eq!(0.5, matrix[(2, 1)]);
+    }
+
+    #[test]
+    fn test_map_in_place() {
+        let mut matrix = Matrix2D::new(3, 6);
+        matrix.fill(0.25);
+        matrix[(5, 2)] = 1.0;
+        matrix.map_in_place(|value| 1.0 - value);
+
+        assert_eq!(18, matrix.len());
+        assert_eq!(0.0, matrix[(5, 2)]);
+        assert_eq!(0.75, matrix[(0, 0)]);
+    }
+
+    #[test]
+    fn test_iter_xy() {
+        let matrix = Matrix2D::from_vec(3, 6, (0..18).collect()).unwrap();
+
+        assert_eq!(Some((1, 1, &7)), matrix.iter_xy().nth(7));
+        assert!(matrix
+            .iter_xy()
+            .all(|(x, y, value)| matrix[(x, y)] == *value));
+    }
+
+    #[test]
+    fn test_iter_xy_mut() {
+        let mut matrix = Matrix2D::new(3, 6);
+        for (x, y, value) in matrix.iter_xy_mut() {
+            *value = x * 10 + y;
+        }
+
+        assert_eq!(11, matrix[(1, 1)]);
+        assert_eq!(Some(&11), matrix.get(7));
+        assert_eq!(52, matrix[(5, 2)]);
+    }
+
+    #[test]
+    fn test_row() {
+        let matrix = Matrix2D::from_vec(3, 4, (0..12).collect()).unwrap();
+
+        assert_eq!(Some(&[4, 5, 6, 7][..]), matrix.row(1));
+        assert_eq!(None, matrix.row(3));
+        assert_eq!(
+            vec![&[0, 1, 2, 3][..], &[4, 5, 6, 7], &[8, 9, 10, 11]],
+            matrix.rows().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_col() {
+        let matrix = Matrix2D::from_vec(3, 4, (0..12).collect()).unwrap();
+
+        assert_eq!(vec![2, 6, 10], matrix.col(2).copied().collect::<Vec<_>>());
+        assert_eq!(0, matrix.col(4).count());
+        assert_eq!(
+            vec![vec![0, 4, 8], vec![1, 5, 9], vec![2, 6, 10], vec![3, 7, 11]],
+            matrix
+                .cols()
+                .map(|col| col.copied().collect::<Vec<_>>())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_resize_grow() {
+        let mut matrix = Matrix2D::new(2, 3);
+        matrix[(0, 0)] = 1.0;
+        matrix[(2, 1)] = 2.0;
+        matrix.resize(3, 4);
+
+        assert_eq!(3, matrix.h());
+        assert_eq!(4, matrix.w());
+        assert_eq!(12, matrix.len());
+        assert_eq!(1.0, matrix[(0, 0)]);
+        assert_eq!(2.0, matrix[(2, 1)]);
+        assert_eq!(0.0, matrix[(3, 0)]);
+        assert_eq!(0.0, matrix[(3, 1)]);
+        assert_eq!(0.0, matrix[(0, 2)]);
+        assert_eq!(3.0, matrix.iter().sum::<f32>());
+    }
+
+    #[test]
+    fn test_resize_shrink() {
+        let mut matrix = Matrix2D::new(3, 4);
+        matrix[(1, 1)] = 1.0;
+        matrix[(3, 2)] = 2.0;
+        matrix.resize(2, 2);
+
+        assert_eq!(2, matrix.h());
+        assert_eq!(2, matrix.w());
+        assert_eq!(4, matrix.len());
+        assert_eq!(1.0, matrix[(1, 1)]);
+        assert_eq!(1.0, matrix.iter().sum::<f32>());
+    }
+
+    #[test]
+    fn test_from_image() {
+        let mut image = image::GrayImage::new(3, 2);
+        image.put_pixel(0, 0, image::Luma([0]));
+        image.put_pixel(1, 0, image::Luma([255]));
+        image.put_pixel(2, 1, image::Luma([51]));
+
+        let path = std::env::temp_dir().join("diffuser_test_from_image.png");
+        image.save(&path).unwrap();
+        let matrix = Matrix2D::from_image(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(2, matrix.h());
+        assert_eq!(3, matrix.w());
+        assert_eq!(DEFAULT_MAX_VALUE, matrix[(0, 0)]);
+        assert_eq!(0.0, matrix[(1, 0)]);
+        assert_eq!(0.8 * DEFAULT_MAX_VALUE, matrix[(2, 1)]);
+    }
+
+    #[test]
+    fn test_from_rgba() {
+        let frame = [255, 255, 255, 0xff, 0, 0, 0, 0xff, 204, 204, 204, 0xff];
+        let matrix = Matrix2D::try_from((&frame[..], 3, 1)).unwrap();
+        assert_eq!(&[0.0, 1.0, 0.2][..], matrix.as_slice());
+
+        assert!(matches!(
+            Matrix2D::from_rgba(&frame[..8], 3, 1),
+            Err(app::Error::InvalidFrameLength {
+                len: 8,
+                width: 3,
+                height: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn test_from_missing_image_is_an_error() {
+        let path = std::env::temp_dir().join("diffuser_test_no_such_image.png");
+        assert!(Matrix2D::from_image(&path).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let mut matrix = Matrix2D::with_boundary(3, 6, BoundaryMode::Wrap);
+        matrix[(1, 1)] = 0.5;
+        matrix[(5, 2)] = DEFAULT_MAX_VALUE;
+
+        let json = serde_json::to_string(&matrix).unwrap();
+        let deserialized: Matrix2D = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(BoundaryMode::Wrap, deserialized.boundary_mode());
+        assert_eq!(matrix.h(), deserialized.h());
+        assert_eq!(matrix.w(), deserialized.w());
+        assert!(matrix.iter().eq(deserialized.iter()));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_deserializing_mismatched_cells_is_an_error() {
+        let json = r#"{"cells":[0.0,1.0,2.0],"width":2,"height":2}"#;
+        let error = serde_json::from_str::<Matrix2D>(json).err().unwrap();
+
+        assert!(error.to_string().contains("needs 4 cells but 3 were given"));
+    }
+
+    #[test]
+    fn test_from_vec_round_trip() {
+        let cells = vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0];
+        let mut matrix = Matrix2D::from_vec(2, 3, cells.clone()).unwrap();
+
+        assert_eq!(&cells[..], matrix.as_slice());
+        assert_eq!(5.0, matrix[(2, 1)]);
+
+        matrix.as_mut_slice()[1] = 9.0;
+        assert_eq!(9.0, matrix[(1, 0)]);
+    }
+
+    #[test]
+    fn test_from_vec_with_wrong_length_is_an_error() {
+        assert!(matches!(
+            Matrix2D::from_vec(2, 3, vec![0.0; 5]),
+            Err(app::Error::InvalidMatrixLength {
+                len: 5,
+                width: 3,
+                height: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn test_statistics() {
+        let matrix = Matrix2D::from_vec(2, 3, vec![0.0, 4.0, -1.0, 0.0, 2.5, 0.5]).unwrap();
+
+        assert_eq!(6.0, matrix.sum());
+        assert_eq!(1.0, matrix.mean());
+        assert_eq!(Some(4.0), matrix.max());
+        assert_eq!(Some(-1.0), matrix.min());
+        assert_eq!(4, matrix.count_nonzero());
+    }
+
+    #[test]
+    fn test_count_where() {
+        let matrix = Matrix2D::from_vec(2, 3, vec![0.9, 0.1, 0.8, 0.85, 1.0, 0.5]).unwrap();
+
+        assert_eq!(3, matrix.count_where(|value| value > 0.8));
+        assert_eq!(0, matrix.count_where(|value| value < 0.0));
+        assert_eq!(6, matrix.count_where(|_| true));
+    }
+
+    #[test]
+    fn test_statistics_of_empty_matrix() {
+        let matrix = Matrix2Df::new(0, 0);
+
+        assert_eq!(0.0, matrix.sum());
+        assert_eq!(0.0, matrix.mean());
+        assert_eq!(None, matrix.max());
+        assert_eq!(None, matrix.min());
+        assert_eq!(0, matrix.count_nonzero());
+    }
+
+    #[test]
+    fn test_normalize() {
+        let mut matrix =
+            Matrix2D::from_vec(2, 3, vec![100.0, 300.0, 150.0, 200.0, 100.0, 300.0]).unwrap();
+        matrix.normalize();
+
+        assert_eq!(Some(0.0), matrix.min());
+        assert_eq!(Some(1.0), matrix.max());
+        assert_eq!(&[0.0, 1.0, 0.25, 0.5, 0.0, 1.0][..], matrix.as_slice());
+    }
+
+    #[test]
+    fn test_normalize_flat_matrix_is_a_no_op() {
+        let mut matrix = Matrix2Df::new(2, 2);
+        matrix.fill(7.0);
+        matrix.normalize();
+        assert!(matrix.iter().all(|value| *value == 7.0));
+
+        Matrix2Df::new(0, 0).normalize();
+    }
+
+    #[test]
+    fn test_elementwise_ops() {
+        let mut matrix = Matrix2D::from_vec(2, 2, vec![1.0, 2.0, 3.0, 4.0]).unwrap();
+        let other = Matrix2D::from_vec(2, 2, vec![0.5, 0.5, 1.0, -1.0]).unwrap();
+
+        matrix += &other;
+        assert_eq!(&[1.5, 2.5, 4.0, 3.0][..], matrix.as_slice());
+        matrix -= &other;
+        assert_eq!(&[1.0, 2.0, 3.0, 4.0][..], matrix.as_slice());
+        matrix.mul_scalar(0.5);
+        assert_eq!(&[0.5, 1.0, 1.5, 2.0][..], matrix.as_slice());
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't combine a 2x2 matrix with a 3x2 one")]
+    fn test_elementwise_ops_need_matching_sizes() {
+        let mut matrix = Matrix2Df::new(2, 2);
+        matrix += &Matrix2Df::new(2, 3);
+    }
+
+    fn stamp() -> Matrix2Df {
+        Matrix2Df::from_vec(2, 2, vec![1.0, 2.0, 3.0, 4.0]).unwrap()
+    }
+
+    #[test]
+    fn test_blit_interior() {
+        let mut matrix = Matrix2Df::new(4, 4);
+        matrix.fill(2.5);
+        matrix.blit(&stamp(), 1, 2, BlendMode::Replace);
+
+        assert_eq!(Some(&[2.5, 2.5, 2.5, 2.5][..]), matrix.row(1));
+        assert_eq!(Some(&[2.5, 1.0, 2.0, 2.5][..]), matrix.row(2));
+        assert_eq!(Some(&[2.5, 3.0, 4.0, 2.5][..]), matrix.row(3));
+
+        matrix.blit(&stamp(), 1, 2, BlendMode::Add);
+        assert_eq!(Some(&[2.5, 2.0, 4.0, 2.5][..]), matrix.row(2));
+
+        matrix.fill(2.5);
+        matrix.blit(&stamp(), 1, 2, BlendMode::Max);
+        assert_eq!(Some(&[2.5, 2.5, 2.5, 2.5][..]), matrix.row(2));
+        assert_eq!(Some(&[2.5, 3.0, 4.0, 2.5][..]), matrix.row(3));
+    }
+
+    #[test]
+    fn test_blit_clips_off_the_right_edge() {
+        let mut matrix = Matrix2Df::new(3, 3);
+        matrix.blit(&stamp(), 2, 0, BlendMode::Replace);
+
+        assert_eq!(Some(&[0.0, 0.0, 1.0][..]), matrix.row(0));
+        assert_eq!(Some(&[0.0, 0.0, 3.0][..]), matrix.row(1));
+        assert_eq!(4.0, matrix.sum());
+
+        // Entirely off the matrix
+        matrix.blit(&stamp(), 3, 2, BlendMode::Replace);
+        matrix.blit(&stamp(), 0, 3, BlendMode::Replace);
+        assert_eq!(4.0, matrix.sum());
+    }
+
+    #[test]
+    fn test_write_csv() {
+        let matrix =
+            Matrix2Df::from_vec(2, 3, vec![0.0, 1.5, 2.0, 1.0 / 3.0, 10.0, -0.25]).unwrap();
+        let mut csv = Vec::new();
+        matrix.write_csv(&mut csv).unwrap();
+
+        assert_eq!(
+            "0.000,1.500,2.000\n0.333,10.000,-0.250\n",
+            String::from_utf8(csv).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_calculate_index_from_xy() {
+        let (width, height) = (6, 3);
+        assert_eq!(7, calculate_index_from_xy(1, 1, width));
+        assert_eq!(0, calculate_index_from_xy(0, 0, width));
+        assert_eq!(width * height - 1, calculate_index_from_xy(5, 2, width));
+    }
+
+    #[test]
+    fn test_calculate_xy_from_index() {
+        let width = 6;
+        assert_eq!((1, 1), calculate_xy_from_index(7, width));
+        assert_eq!((5, 0), calculate_xy_from_index(5, width));
+        assert_eq!((0, 2), calculate_xy_from_index(12, width));
+    }
+
+    #[test]
+    fn test_xy_index_round_trip() {
+        let width = 6;
+        for &(x, y) in &[(0, 0), (1, 1), (5, 0), (0, 2), (3, 1), (5, 2)] {
+            let index = calculate_index_from_xy(x, y, width);
+            assert_eq!((x, y), calculate_xy_from_index(index, width));
+        }
+    }
+
+    #[test]
+    fn test_no_neighbour_index_past_bottom_right_cell() {
+        let matrix = Matrix2Df::new(3, 6);
+        let bottom_right = matrix.len() - 1;
+
+        assert_eq!(
+            None,
+            matrix.get_neighbour_index(bottom_right, Direction::East)
+        );
+        assert_eq!(
+            None,
+            matrix.get_neighbour_index(bottom_right, Direction::SouthEast)
+        );
+        assert_eq!(
+            None,
+            matrix.get_neighbour_index(bottom_right, Direction::South)
+        );
+    }
+
+    #[test]
+    fn test_clamp_is_the_default_boundary_mode() {
+        let matrix = Matrix2Df::new(3, 6);
+        assert_eq!(BoundaryMode::Clamp, matrix.boundary_mode());
+        assert_eq!(None, matrix.get_neighbour_index(6, Direction::West));
+    }
+
+    #[test]
+    fn test_wrap_west_of_first_column_is_last_column() {
+        let matrix = Matrix2Df::with_boundary(3, 6, BoundaryMode::Wrap);
+        assert_eq!(Some(11), matrix.get_neighbour_index(6, Direction::West));
+        assert_eq!(Some(6), matrix.get_neighbour_index(11, Direction::East));
+    }
+
+    #[test]
+    fn test_wrap_corners() {
+        let matrix = Matrix2Df::with_boundary(3, 6, BoundaryMode::Wrap);
+        assert_eq!(
+            Some(17),
+            matrix.get_neighbour_index(0, Direction::NorthWest)
+        );
+        assert_eq!(Some(12), matrix.get_neighbour_index(0, Direction::North));
+        assert_eq!(
+            Some(0),
+            matrix.get_neighbour_index(17, Direction::SouthEast)
+        );
+        assert_eq!(None, matrix.get_neighbour_index(18, Direction::East));
+    }
+
+    #[test]
+    fn test_index_by_xy() {
+        let mut matrix = Matrix2D::new(3, 6);
+        matrix[(1, 1)] = 0.5;
+
+        assert_eq!(0.5, matrix[(1, 1)]);
+        assert_eq!(Some(&0.5), matrix.get(7));
+        assert_eq!(0.0, matrix[(5, 2)]);
+    }
+
+    #[test]
+    fn test_set() {
+        let mut matrix = Matrix2D::new(3, 6);
+        matrix.set(1, 1, 0.5).unwrap();
+
+        assert_eq!(0.5, matrix[(1, 1)]);
+        assert_eq!(0.5, matrix.sum());
+    }
+
+    #[test]
+    fn test_set_out_of_bounds() {
+        let mut matrix = Matrix2D::new(3, 6);
+
+        assert!(matches!(
+            matrix.set(6, 0, 0.5),
+            Err(app::Error::InvalidXyIndex {
+                x: 6,
+                y: 0,
+                width: 6,
+                height: 3
+            })
+        ));
+        assert!(matrix.set(0, 3, 0.5).is_err());
+        assert_eq!(0.0, matrix.sum());
+    }
+
+    #[test]
+    fn test_get_by_vector() {
+        let mut matrix = Matrix2D::new(3, 6);
+        *matrix.get_mut_v(Vector2::new(1, 1)).unwrap() = 0.5;
+
+        assert_eq!(Some(&0.5), matrix.get_v(Vector2::new(1, 1)));
+        assert_eq!(Some(&0.5), matrix.get(7));
+        assert_eq!(Some(&0.0), matrix.get_v(Vector2::new(5, 2)));
+    }
+
+    #[test]
+    fn test_get_by_vector_out_of_bounds() {
+        let mut matrix = Matrix2Df::new(3, 6);
+
+        assert_eq!(None, matrix.get_v(Vector2::new(6, 0)));
+        assert_eq!(None, matrix.get_v(Vector2::new(0, 3)));
+        assert_eq!(None, matrix.get_mut_v(Vector2::new(6, 0)));
+        assert_eq!(None, matrix.get_mut_v(Vector2::new(0, 3)));
+    }
+
+    #[test]
+    #[should_panic(expected = "No cell at {x: 6, y: 0}")]
+    fn test_index_by_xy_out_of_bounds() {
+        let matrix = Matrix2Df::new(3, 6);
+        let _ = matrix[(6, 0)];
+    }
+
+    #[test]
+    #[should_panic(expected = "No cell at {x: 0, y: 3}")]
+    fn test_index_mut_by_xy_out_of_bounds() {
+        let mut matrix = Matrix2D::new(3, 6);
+        matrix[(0, 3)] = 1.0;
+    }
+
     #[test]
     fn test_index_to_the_northwest() {
         let (height, width) = (3, 6);
@@ -322,6 +1498,22 @@ mod test {
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn test_no_index_to_the_south_from_any_cell_in_last_row() {
+        let (height, width) = (3, 6);
+        for index in 12..18 {
+            assert_eq!(-1, index_to_the_south(index, height, width));
+        }
+    }
+
+    #[test]
+    fn test_south_and_north_are_opposites() {
+        let (height, width) = (3, 6);
+        let south = index_to_the_south(9, height, width);
+        assert_ne!(index_to_the_north(9, height, width), south);
+        assert_eq!(9, index_to_the_north(south, height, width));
+    }
+
     #[test]
     fn test_no_index_to_the_southwest() {
         let (height, width) = (3, 6);
@@ -329,4 +1521,114 @@ mod test {
         let actual = index_to_the_southwest(6, height, width);
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn test_opposite() {
+        assert_eq!(Direction::South, Direction::North.opposite());
+        for direction in &Direction::ALL {
+            assert_eq!(*direction, direction.opposite().opposite());
+        }
+    }
+
+    #[test]
+    fn test_offset() {
+        assert_eq!((1, -1), Direction::NorthEast.offset());
+        for direction in &Direction::ALL {
+            let (dx, dy) = direction.offset();
+            assert_eq!((-dx, -dy), direction.opposite().offset());
+        }
+    }
+
+    #[test]
+    fn test_direction_display_round_trip() {
+        for direction in Direction::ALL.iter() {
+            assert_eq!(*direction, direction.to_string().parse().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_direction_from_str() {
+        assert_eq!(Direction::North, "N".parse().unwrap());
+        assert_eq!(Direction::NorthEast, "ne".parse().unwrap());
+        assert_eq!(Direction::SouthWest, "South-West".parse().unwrap());
+        assert_eq!(Direction::West, "WEST".parse().unwrap());
+        assert!(matches!(
+            "up".parse::<Direction>(),
+            Err(app::Error::UnknownDirection { .. })
+        ));
+    }
+
+    #[test]
+    fn test_u8_matrix() {
+        let mut matrix: Matrix2D<u8> = Matrix2D::new(2, 3);
+        assert!(matrix.iter().all(|value| *value == 0));
+
+        *matrix.get_mut(4).unwrap() = 200;
+        matrix[(0, 0)] = 7;
+        assert_eq!(Some(&200), matrix.get(4));
+        assert_eq!(207, matrix.iter().map(|value| *value as u32).sum::<u32>());
+        assert_eq!(None, matrix.get(6));
+    }
+
+    #[test]
+    fn test_f64_matrix() {
+        let mut matrix: Matrix2D<f64> = Matrix2D::with_boundary(2, 2, BoundaryMode::Wrap);
+        for (index, value) in matrix.iter_mut().enumerate() {
+            *value = index as f64 * 0.5;
+        }
+
+        assert_eq!(Some(&1.5), matrix.get(3));
+        assert_eq!(
+            Some(&1.0),
+            matrix.get_neighbouring_cell(0, Direction::South)
+        );
+        matrix.clear();
+        assert_eq!(0.0, matrix.iter().sum::<f64>());
+    }
+
+    #[test]
+    fn test_corner_cell_has_three_neighbours() {
+        let matrix = Matrix2Df::new(3, 6);
+        let directions: Vec<_> = matrix
+            .neighbours(0)
+            .map(|(direction, _)| direction)
+            .collect();
+
+        assert_eq!(
+            vec![Direction::East, Direction::South, Direction::SouthEast],
+            directions
+        );
+        assert_eq!(3, matrix.neighbour_indices(17).count());
+    }
+
+    #[test]
+    fn test_center_cell_has_eight_neighbours() {
+        let mut matrix = Matrix2Df::new(3, 6);
+        for (index, value) in matrix.iter_mut().enumerate() {
+            *value = index as f32;
+        }
+
+        let values: Vec<_> = matrix.neighbours(8).map(|(_, value)| *value).collect();
+        assert_eq!(vec![1.0, 2.0, 3.0, 7.0, 9.0, 13.0, 14.0, 15.0], values);
+    }
+
+    #[test]
+    fn test_neighbour_of_center_cell() {
+        let matrix = Matrix2Df::new(3, 6);
+        assert_eq!(Some((3, 3, 0)), matrix.neighbour(8, Direction::NorthEast));
+    }
+
+    #[test]
+    fn test_no_neighbour_past_corner() {
+        let matrix = Matrix2Df::new(3, 6);
+        assert_eq!(None, matrix.neighbour(0, Direction::NorthEast));
+        assert_eq!(None, matrix.neighbour(0, Direction::West));
+        assert_eq!(None, matrix.neighbour(17, Direction::SouthEast));
+    }
+
+    #[test]
+    fn test_wrapped_corner_cell_has_eight_neighbours() {
+        let matrix = Matrix2Df::with_boundary(3, 6, BoundaryMode::Wrap);
+        assert_eq!(8, matrix.neighbours(0).count());
+    }
 }
@@ -0,0 +1,95 @@
+use crate::matrix::Matrix2D;
+
+/// A matrix holding a red, green, and blue value for every cell. Each channel is stored as its own `Matrix2D` so
+/// that the channels can be diffused independently of one another.
+#[derive(Clone)]
+pub struct Matrix2DRgb {
+    channels: [Matrix2D; 3],
+}
+
+impl Matrix2DRgb {
+    pub fn new(height: usize, width: usize) -> Self {
+        Self {
+            channels: [
+                Matrix2D::new(height, width),
+                Matrix2D::new(height, width),
+                Matrix2D::new(height, width),
+            ],
+        }
+    }
+
+    pub fn h(&self) -> usize {
+        self.channels[0].h()
+    }
+
+    pub fn w(&self) -> usize {
+        self.channels[0].w()
+    }
+
+    pub fn len(&self) -> usize {
+        self.channels[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.channels[0].is_empty()
+    }
+
+    /// Get the `[r, g, b]` values of the cell at `index`
+    pub fn get(&self, index: usize) -> Option<[f32; 3]> {
+        let [r, g, b] = &self.channels;
+
+        Some([*r.get(index)?, *g.get(index)?, *b.get(index)?])
+    }
+
+    pub fn channel(&self, channel: usize) -> &Matrix2D {
+        &self.channels[channel]
+    }
+
+    pub fn channel_mut(&mut self, channel: usize) -> &mut Matrix2D {
+        &mut self.channels[channel]
+    }
+
+    pub fn channels_mut(&mut self) -> impl Iterator<Item = &mut Matrix2D> {
+        self.channels.iter_mut()
+    }
+
+    pub fn resize(&mut self, new_height: usize, new_width: usize) {
+        for channel in self.channels.iter_mut() {
+            channel.resize(new_height, new_width);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for channel in self.channels.iter_mut() {
+            channel.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_get_combines_channels() {
+        let mut matrix = Matrix2DRgb::new(3, 6);
+        matrix.channel_mut(0)[(1, 1)] = 0.25;
+        matrix.channel_mut(2)[(1, 1)] = 0.75;
+
+        assert_eq!(Some([0.25, 0.0, 0.75]), matrix.get(7));
+        assert_eq!(Some([0.0, 0.0, 0.0]), matrix.get(0));
+        assert_eq!(None, matrix.get(18));
+    }
+
+    #[test]
+    fn test_resize_resizes_every_channel() {
+        let mut matrix = Matrix2DRgb::new(3, 6);
+        matrix.resize(4, 8);
+
+        assert_eq!(32, matrix.len());
+        for channel in 0..3 {
+            assert_eq!(4, matrix.channel(channel).h());
+            assert_eq!(8, matrix.channel(channel).w());
+        }
+    }
+}
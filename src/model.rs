@@ -1,27 +1,351 @@
 use crate::app;
-use crate::constants::{DEFAULT_DECAY_FACTOR, DEFAULT_MAX_VALUE, DEFAULT_VALUE_CUTOFF};
-use crate::matrix::{calculate_index_from_xy, Direction, Matrix2D};
+use crate::config::SimulationConfig;
+use crate::constants::{
+    ANTI_DIFFUSION_STRENGTH, DEFAULT_BRUSH_HARDNESS, DEFAULT_BRUSH_RADIUS, DEFAULT_ERASE_STRENGTH,
+    DEFAULT_FLOW_RATE, DEFAULT_GAMMA, DEFAULT_GAUSSIAN_SIGMA, DEFAULT_GRID_SPACING,
+    DEFAULT_NOISE_AMOUNT, DEFAULT_POSTERIZE_LEVELS, DEFAULT_SETTLE_EPSILON, DEFAULT_THRESHOLD,
+    FLOOD_FILL_TOLERANCE, GRID_COLOR, GRID_OPACITY, MAX_EXPONENTIAL_DECAY_RATE, MAX_FRAME_TIME,
+    MAX_GAMMA, MAX_ZOOM, MIN_GAMMA, MIN_ZOOM, PAINT_COLORS, PAINT_LOG_INTERVAL_MS, SETTLE_TICKS,
+};
+use crate::decay::{self, Decay};
+use crate::history::History;
+use crate::hud;
+use crate::kernel::Kernel;
+use crate::matrix::{
+    calculate_index_from_xy, calculate_xy_from_index, BlendMode, BoundaryMode, Direction, Matrix2D,
+};
+use crate::matrix_rgb::Matrix2DRgb;
+use crate::palette::Palette;
+use crate::patterns::Pattern;
+use crate::symmetry::Symmetry;
 use crate::{rect::Rect, vector2::Vector2};
+use image::ColorType;
 use line_drawing::Bresenham;
-use log::debug;
+use log::{debug, log_enabled, trace, Level};
+use rand::{rngs::StdRng, seq::index, SeedableRng};
 use rayon::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::path::Path;
+use std::time::{Duration, Instant};
+#[cfg(feature = "serde")]
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+};
+
+#[cfg(feature = "serde")]
+#[derive(Serialize)]
+struct SavedStateRef<'a> {
+    base_matrix: &'a Matrix2D,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct SavedState {
+    base_matrix: Matrix2D,
+}
+
+/// Which canvas gets painted, diffused, and drawn
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorMode {
+    /// A single channel of ink, drawn dark on a light background
+    Grayscale,
+    /// Independent red, green, and blue channels of light, drawn on a dark background
+    Rgb,
+}
+
+/// A copy of the canvas a stroke painted on. Undoing the stroke puts it back on that canvas, whichever `ColorMode` is
+/// in use by then.
+#[derive(Clone)]
+pub enum Snapshot {
+    Grayscale(Matrix2D),
+    Rgb(Matrix2DRgb),
+}
+
+impl Snapshot {
+    /// Put this snapshot back on the canvas it was taken from, returning what was there instead
+    fn restore(self, base_matrix: &mut Matrix2D, rgb_matrix: &mut Matrix2DRgb) -> Self {
+        match self {
+            Snapshot::Grayscale(canvas) => {
+                Snapshot::Grayscale(std::mem::replace(base_matrix, canvas))
+            }
+            Snapshot::Rgb(canvas) => Snapshot::Rgb(std::mem::replace(rgb_matrix, canvas)),
+        }
+    }
+}
+
+/// How ink spreads from a cell to the cells around it each tick
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DiffusionKernel {
+    /// Split each cell evenly between itself and its neighbours
+    Uniform,
+    /// Blur the canvas with a Gaussian of standard deviation `Model::sigma`
+    Gaussian,
+}
+
+/// Whether ink spreads out or gathers in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DiffusionMode {
+    /// Spread ink with `Model::diffusion_kernel`, which blurs the canvas
+    Diffuse,
+    /// Run diffusion in reverse by pushing each cell away from the average of its neighbours, which sharpens edges
+    /// instead of blurring them. Neighbours are weighed with `Model::kernel`.
+    AntiDiffuse,
+}
+
+/// How ink fades from the canvas over time
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DecayMode {
+    /// Every cell loses `decay_factor` ink a second, no matter how much it holds
+    Linear,
+    /// Every cell loses a `decay_factor` fraction of its ink a second, so bright cells fade faster than dim ones and
+    /// nothing drops straight to zero. `decay_factor` is capped at `MAX_EXPONENTIAL_DECAY_RATE`.
+    Exponential,
+}
+
+impl DecayMode {
+    /// How a cell decays over a tick `frame_time` seconds long, losing `rate` a second
+    pub fn per_tick(self, rate: f32, frame_time: f32) -> Decay {
+        match self {
+            DecayMode::Linear => Decay::Linear(rate * frame_time),
+            DecayMode::Exponential => {
+                Decay::Exponential((1.0 - self.cap_rate(rate)).clamp(0.0, 1.0).powf(frame_time))
+            }
+        }
+    }
+
+    /// `rate` capped at the most this mode can decay a second
+    pub fn cap_rate(self, rate: f32) -> f32 {
+        match self {
+            DecayMode::Linear => rate,
+            DecayMode::Exponential => rate.min(MAX_EXPONENTIAL_DECAY_RATE),
+        }
+    }
+}
+
+/// Which of the cells around a cell its ink spills into with the uniform kernel
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NeighbourhoodKind {
+    /// The four cells that share an edge with it
+    VonNeumann,
+    /// All eight cells that share an edge or a corner with it
+    Moore,
+}
+
+impl NeighbourhoodKind {
+    /// Whether the neighbour in `direction` is part of this neighbourhood
+    pub fn includes(self, direction: Direction) -> bool {
+        use Direction::*;
+        match self {
+            NeighbourhoodKind::VonNeumann => matches!(direction, North | West | East | South),
+            NeighbourhoodKind::Moore => true,
+        }
+    }
+
+    /// How many neighbours a cell has when none of them are off the edge of the canvas
+    pub fn size(self) -> usize {
+        match self {
+            NeighbourhoodKind::VonNeumann => 4,
+            NeighbourhoodKind::Moore => 8,
+        }
+    }
+}
+
+/// How cell values are quantized before they're drawn. This only changes what's drawn, never the simulation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderMode {
+    /// Draw values as they are
+    Smooth,
+    /// Draw cells below the threshold as empty, and cells at or above it as full
+    Threshold(f32),
+    /// Round values to this many evenly spaced levels, from empty to full
+    Posterize(u8),
+}
+
+impl RenderMode {
+    /// The render mode that comes after this one, wrapping back around to `Smooth`
+    pub fn next(self) -> Self {
+        match self {
+            RenderMode::Smooth => RenderMode::Threshold(DEFAULT_THRESHOLD),
+            RenderMode::Threshold(_) => RenderMode::Posterize(DEFAULT_POSTERIZE_LEVELS),
+            RenderMode::Posterize(_) => RenderMode::Smooth,
+        }
+    }
+
+    /// Quantize a value that's already been clamped to `[0, 1]`
+    fn apply(self, value: f32) -> f32 {
+        match self {
+            RenderMode::Smooth => value,
+            RenderMode::Threshold(threshold) => {
+                if value < threshold {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+            RenderMode::Posterize(levels) => {
+                // Fewer than two levels can't tell empty and full cells apart
+                let steps = (levels.max(2) - 1) as f32;
+                (value * steps).round() / steps
+            }
+        }
+    }
+}
+
+/// Lets a message through at most once every `interval`, so logging something every frame doesn't flood the log
+#[derive(Clone, Copy, Debug)]
+struct Throttle {
+    interval: Duration,
+    last: Option<Instant>,
+}
+
+impl Throttle {
+    fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last: None,
+        }
+    }
+
+    /// Whether a message can go out at `now`. If it can, the next one has to wait another `interval`.
+    fn ready(&mut self, now: Instant) -> bool {
+        match self.last {
+            Some(last) if now.saturating_duration_since(last) < self.interval => false,
+            _ => {
+                self.last = Some(now);
+                true
+            }
+        }
+    }
+}
+
+/// A callback `Model::update` hands the model to once it's done. It has to be `Send + Sync` because drawing shares the
+/// model between threads.
+pub type TickCallback = Box<dyn FnMut(&Model) + Send + Sync>;
 
 /// Representation of the application state. In this example, a box will bounce around the screen.
 pub struct Model {
+    /// Painting adds ink to the grayscale canvas a little at a time, so holding the brush still builds it up, rather
+    /// than setting cells to `paint_value` straight away
+    pub accumulate_paint: bool,
+    /// How many times wider than they are tall cells are drawn. Uniform diffusion and anti-diffusion weigh neighbours
+    /// to the sides less when it's over 1, and more when it's under 1, so ink spreads evenly on screen.
+    pub aspect: f32,
+    /// Scratch space that the next tick is written into before it's swapped with `base_matrix`
+    pub back_matrix: Matrix2D,
+    /// A layer drawn behind the grayscale canvas that never diffuses or decays, the same size as the canvas
+    pub background: Option<Matrix2D>,
+    /// How each cell of `background` is combined with the cell of the canvas over it when they're drawn
+    pub background_blend: BlendMode,
+    /// The front buffer, the grayscale canvas that gets painted and drawn
     pub base_matrix: Matrix2D,
+    /// How sharp the edge of the grayscale brush is, in `[0, 1]`. At 1 it paints a solid disc, lower values feather
+    /// the edge out so that at 0 a cell on the brush's radius gets half as much ink as the center.
+    pub brush_hardness: f32,
+    pub brush_radius: usize,
+    /// When a stroke leaves the canvas, keep painting along the edge nearest the cursor instead of stopping where it
+    /// left
+    pub clamp_strokes: bool,
+    pub color_mode: ColorMode,
+    /// Decay, cutoff, and the most ink a cell can hold. `decay_factor` can be changed while the simulation runs.
+    pub config: SimulationConfig,
+    /// Keep the spillover that would have gone off the edge of the canvas, so only decay removes ink
+    pub conserve_mass: bool,
+    /// How much each cell's decay is multiplied by, set with `set_decay_map`. Every cell decays the same when it's
+    /// `None`.
+    decay_map: Option<Matrix2D>,
+    pub decay_mode: DecayMode,
+    pub diffusion_kernel: DiffusionKernel,
+    pub diffusion_mode: DiffusionMode,
+    /// Erasing empties cells outright. When it's `false` erasing takes `erase_strength` out of them instead.
+    pub erase_hard: bool,
+    /// How much ink the soft eraser takes out of a cell each time it passes over it
+    pub erase_strength: f32,
+    /// How many times over `paint_value` the brush lays down each second when `accumulate_paint` is set
+    pub flow_rate: f32,
+    /// The frame rate shown in the HUD, kept up to date by whatever is drawing the model
+    pub fps: f32,
+    /// While it's set the brush freezes the cells it passes over instead of painting them, and erasing thaws them
+    pub freezing: bool,
+    /// Cells that are `true` here keep their value, they don't spill over into their neighbours or decay
+    pub frozen: Matrix2D<bool>,
+    /// Cell values are raised to the power of `1.0 / gamma` before they're drawn. Clamped to `[MIN_GAMMA, MAX_GAMMA]`.
+    pub gamma: f32,
+    /// How many cells apart the lines of the grid overlay are, it isn't drawn if this is 0
+    pub grid_spacing: usize,
+    /// Undo and redo snapshots of the grayscale canvas
+    pub history: History<Snapshot>,
+    /// Draw the canvas with its colors inverted, so grayscale ink is bright on a dark background. The simulation is
+    /// unaffected.
+    pub invert_display: bool,
+    /// How the uniform diffusion kernel shares spillover between a cell and its neighbours
+    pub kernel: Kernel,
     pub left_click_is_held_down: bool,
-    pub modifier_matrix: Matrix2D,
+    /// The most ink uniform diffusion moves from a cell into any one neighbour each tick, the cell keeps whatever's
+    /// over it. Spreading is uncapped when it's `None`.
+    pub max_transfer: Option<f32>,
     pub mouse_xy: Vector2<f32>,
+    pub neighbourhood: NeighbourhoodKind,
+    /// How much ink each drop of noise adds to a cell
+    pub noise_amount: f32,
+    /// The fraction of cells that get a drop of noise each tick, noise is off when this is zero
+    pub noise_rate: f32,
+    /// Called with the model at the end of every `update`, for embedders that want to watch the simulation
+    pub on_tick: Option<TickCallback>,
+    pub paint_color: [f32; 3],
+    /// How much ink the brush lays down on the grayscale canvas
+    pub paint_value: f32,
+    /// Keeps messages about painting from flooding the debug log while the brush is held down
+    paint_log_throttle: Throttle,
+    /// The colormap the grayscale canvas is drawn with
+    pub palette: Palette,
+    /// The cell drawn in the top-left corner when zoomed in. Kept within the canvas by `pan_by` and `set_zoom`.
+    pub pan_offset: Vector2<f32>,
+    /// While paused, painting still works but the canvas doesn't diffuse or decay
+    pub paused: bool,
+    pub render_mode: RenderMode,
     pub previous_mouse_xy: Option<Vector2<f32>>,
+    /// The fraction of its ink a cell keeps each tick of uniform diffusion, with the rest shared between its neighbours
+    /// as `kernel` weighs them. When it's `None` the cell keeps whatever share `kernel` gives its center, which is 1/9
+    /// for the uniform kernel.
+    pub retention: Option<f32>,
+    pub rgb_matrix: Matrix2DRgb,
     pub right_click_is_held_down: bool,
+    /// Every randomized feature draws from this, so a seeded model is reproducible
+    pub rng: StdRng,
+    /// The simulation counts as settled once a tick changes the canvas by less than this in total
+    pub settle_epsilon: f32,
+    /// Draw a faint grid over the canvas every `grid_spacing` cells. Only what's drawn changes, never the simulation.
+    pub show_grid: bool,
+    /// Draw the HUD over the canvas with `draw_hud`
+    pub show_hud: bool,
+    /// The standard deviation of the Gaussian kernel, in cells
+    pub sigma: f32,
+    /// How many pixels wide each cell is drawn before zooming, so a big window can be simulated on a smaller canvas.
+    /// The frame `draw` fills is this many times as wide and as tall as the canvas. Treated as 1 if it's 0.
+    pub sim_scale: usize,
+    /// Indices of the cells the soft eraser has already taken ink out of during the current stroke, so holding it still
+    /// or going back over a cell doesn't keep erasing it
+    soft_erased: HashSet<usize>,
+    /// Run a single tick of diffusion on the next update even though the simulation is paused
+    pub step_once: bool,
+    /// Mirror every stroke around the center of the canvas
+    pub symmetry: Symmetry,
+    ticks_without_change: usize,
     pub window_rect: Rect<usize>,
+    /// How many pixels wide each cell is drawn, starting from the top-left corner. Clamped to `[MIN_ZOOM, MAX_ZOOM]`.
+    pub zoom: usize,
 }
 
 impl Model {
     /// Create a new `World` instance that can draw a moving box.
-    pub fn new(window_rect: Rect<usize>) -> Self {
-        let base_matrix = Matrix2D::new(window_rect.h() as usize, window_rect.w() as usize);
-        let modifier_matrix = Matrix2D::new(window_rect.h() as usize, window_rect.w() as usize);
+    pub fn new(config: &SimulationConfig) -> Self {
+        let window_rect = config.resolution;
+        let base_matrix = Matrix2D::new(window_rect.h(), window_rect.w());
+        let back_matrix = Matrix2D::new(window_rect.h(), window_rect.w());
 
         debug!(
             "Created new base_matrix with dimensions (w: {}, h: {})",
@@ -29,32 +353,153 @@ impl Model {
             base_matrix.h()
         );
         debug!(
-            "Created new modifier_matrix with dimensions (w: {}, h: {})",
-            modifier_matrix.w(),
-            modifier_matrix.h()
+            "Created new back_matrix with dimensions (w: {}, h: {})",
+            back_matrix.w(),
+            back_matrix.h()
         );
 
         Self {
+            accumulate_paint: false,
+            aspect: 1.0,
+            back_matrix,
+            background: None,
+            background_blend: BlendMode::Max,
             base_matrix,
+            brush_hardness: DEFAULT_BRUSH_HARDNESS,
+            brush_radius: DEFAULT_BRUSH_RADIUS,
+            clamp_strokes: true,
+            color_mode: ColorMode::Grayscale,
+            config: *config,
+            conserve_mass: false,
+            decay_map: None,
+            decay_mode: DecayMode::Linear,
+            diffusion_kernel: DiffusionKernel::Uniform,
+            diffusion_mode: DiffusionMode::Diffuse,
+            erase_hard: true,
+            erase_strength: DEFAULT_ERASE_STRENGTH,
+            flow_rate: DEFAULT_FLOW_RATE,
+            fps: 0.0,
+            freezing: false,
+            frozen: Matrix2D::new(window_rect.h(), window_rect.w()),
+            gamma: DEFAULT_GAMMA,
+            grid_spacing: DEFAULT_GRID_SPACING,
+            history: History::new(),
+            invert_display: false,
+            kernel: Kernel::uniform(),
             left_click_is_held_down: false,
-            modifier_matrix,
+            max_transfer: None,
             mouse_xy: Vector2::new(0.0, 0.0),
+            neighbourhood: NeighbourhoodKind::Moore,
+            noise_amount: DEFAULT_NOISE_AMOUNT,
+            noise_rate: 0.0,
+            on_tick: None,
+            paint_color: PAINT_COLORS[0],
+            paint_value: config.max_value,
+            paint_log_throttle: Throttle::new(Duration::from_millis(PAINT_LOG_INTERVAL_MS)),
+            palette: Palette::Grayscale,
+            pan_offset: Vector2::new(0.0, 0.0),
+            paused: false,
+            render_mode: RenderMode::Smooth,
             previous_mouse_xy: None,
+            retention: None,
+            rgb_matrix: Matrix2DRgb::new(window_rect.h(), window_rect.w()),
             right_click_is_held_down: false,
+            rng: match config.rng_seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            },
+            settle_epsilon: DEFAULT_SETTLE_EPSILON,
+            show_grid: false,
+            show_hud: false,
+            sigma: DEFAULT_GAUSSIAN_SIGMA,
+            sim_scale: 1,
+            soft_erased: HashSet::new(),
+            step_once: false,
+            symmetry: Symmetry::None,
+            ticks_without_change: 0,
             window_rect,
+            zoom: MIN_ZOOM,
+        }
+    }
+
+    /// Create a model whose canvas starts out as `pattern`. With `rng_seed` set in `config` the canvas is the same
+    /// every time, which is what benchmarks and tests want.
+    pub fn new_seeded(config: &SimulationConfig, pattern: Pattern) -> Self {
+        let mut model = Self::new(config);
+        model.seed_pattern(pattern);
+
+        model
+    }
+
+    /// Change the size of the canvas, keeping whatever was painted in the region that still fits
+    pub fn resize(&mut self, window_rect: Rect<usize>) {
+        self.base_matrix.resize(window_rect.h(), window_rect.w());
+        self.back_matrix.resize(window_rect.h(), window_rect.w());
+        self.rgb_matrix.resize(window_rect.h(), window_rect.w());
+        self.frozen.resize(window_rect.h(), window_rect.w());
+        if let Some(background) = &mut self.background {
+            background.resize(window_rect.h(), window_rect.w());
         }
+        self.window_rect = window_rect;
+        // A decay map drawn for the old size wouldn't line up with the new canvas
+        self.decay_map = None;
+
+        debug!(
+            "Resized matrices to dimensions (w: {}, h: {})",
+            self.window_rect.w(),
+            self.window_rect.h()
+        );
     }
 
+    /// Paint, then advance the simulation by `frame_time` seconds, which is capped at `MAX_FRAME_TIME`. `on_tick` is
+    /// called afterwards, even if the simulation is paused or settled.
     pub fn update(&mut self, frame_time: f32) {
-        assert_eq!(self.base_matrix.len(), self.modifier_matrix.len(), "matrices should be identical length but they are not: base_matrix.len() == {}, modifier_matrix.len() == {}", self.base_matrix.len(), self.modifier_matrix.len());
+        self.advance(frame_time);
+
+        if let Some(mut on_tick) = self.on_tick.take() {
+            on_tick(self);
+            self.on_tick = Some(on_tick);
+        }
+    }
+
+    fn advance(&mut self, frame_time: f32) {
+        let frame_time = frame_time.min(MAX_FRAME_TIME);
+        assert_eq!(self.base_matrix.len(), self.back_matrix.len(), "matrices should be identical length but they are not: base_matrix.len() == {}, back_matrix.len() == {}", self.base_matrix.len(), self.back_matrix.len());
         let mouse_buttons_are_held_down =
             self.left_click_is_held_down || self.right_click_is_held_down;
         if mouse_buttons_are_held_down {
-            let Vector2 { x, y } = self.mouse_xy;
-            let (x, y) = (x.round() as usize, y.round() as usize);
+            let erase = match (self.left_click_is_held_down, self.right_click_is_held_down) {
+                (true, _) => false,
+                (_, true) => true,
+                _ => unreachable!("No other combinations need to be considered"),
+            };
+            let mouse_is_on_canvas = self.window_rect.contains_point(self.mouse_xy);
+            // A stroke that leaves the canvas carries on along its edge, rather than stopping short of it
+            let stroke_xy = if mouse_is_on_canvas {
+                Some(self.mouse_xy)
+            } else if self.clamp_strokes && self.previous_mouse_xy.is_some() {
+                Some(self.window_rect.clamp_point(self.mouse_xy))
+            } else {
+                None
+            };
 
-            if self.window_rect.contains(x, y) {
-                if let Some(Vector2 {
+            if let Some(stroke_xy) = stroke_xy {
+                let Vector2 { x, y } = stroke_xy;
+                let (x, y) = (x.round() as usize, y.round() as usize);
+                // Clicks paint the nearest cell, but strokes that haven't moved a whole cell since the last update are
+                // painted between cells so they don't snap to the grid
+                let is_slow_stroke = self
+                    .previous_mouse_xy
+                    .is_some_and(|previous| previous.distance(&stroke_xy) < 1.0);
+
+                if is_slow_stroke && self.paints_subpixel(erase) {
+                    self.paint_subpixel(stroke_xy, self.paint_value);
+
+                    self.log_painting(format_args!(
+                        "Painting between cells at {{x: {}, y: {}}}",
+                        stroke_xy.x, stroke_xy.y
+                    ));
+                } else if let Some(Vector2 {
                     x: prev_x,
                     y: prev_y,
                 }) = self.previous_mouse_xy
@@ -65,145 +510,2997 @@ impl Model {
                         x as isize,
                         y as isize,
                     );
+                    let canvas =
+                        Rect::new(self.window_rect.h() as isize, self.window_rect.w() as isize);
                     let line_points = Bresenham::new((prev_x, prev_y), (x, y));
                     for (line_x, line_y) in line_points {
-                        if line_x < 0
-                            || line_y < 0
-                            || line_x > self.window_rect.w() as isize
-                            || line_y > self.window_rect.h() as isize
-                        {
+                        if !canvas.contains(line_x, line_y) {
                             continue;
                         }
 
-                        let index = calculate_index_from_xy(
-                            line_x as usize,
-                            line_y as usize,
-                            self.window_rect.w() as usize,
-                        );
-
-                        *self.base_matrix.get_mut(index).expect("invalid index") =
-                            match (self.left_click_is_held_down, self.right_click_is_held_down) {
-                                (true, _) => DEFAULT_MAX_VALUE,
-                                (_, true) => 0.0,
-                                _ => {
-                                    unreachable!("No other combinations need to be considered")
-                                }
-                            };
+                        // Stamp the brush at every point so fast strokes stay continuous
+                        self.paint(line_x as usize, line_y as usize, erase, frame_time);
                     }
 
-                    debug!(
+                    self.log_painting(format_args!(
                         "Painting from {{x: {}, y: {}}} to {{x: {}, y: {}}}",
                         prev_x, prev_y, x, y
-                    );
+                    ));
                 } else {
-                    let index = calculate_index_from_xy(x, y, self.window_rect.w() as usize);
-
-                    // can't fail because we've already checked that coords are in bounds
-                    *self.base_matrix.get_mut(index).expect("invalid index") =
-                        match (self.left_click_is_held_down, self.right_click_is_held_down) {
-                            (true, _) => DEFAULT_MAX_VALUE,
-                            (_, true) => 0.0,
-                            _ => unreachable!("No other combinations need to be considered"),
-                        };
+                    self.paint(x, y, erase, frame_time);
 
-                    debug!("Painting {{x: {}, y: {}}}", x, y);
+                    self.log_painting(format_args!("Painting {{x: {}, y: {}}}", x, y));
                 }
 
                 // We need to store previous mouse positions so we can line draw when the mouse button is held down
-                self.previous_mouse_xy = Some(self.mouse_xy);
+                self.previous_mouse_xy = Some(stroke_xy);
             } else {
-                debug!("Mouse outside canvas bounds {{x: {}, y: {}}}", x, y);
+                let Vector2 { x, y } = self.mouse_xy;
+                self.log_painting(format_args!(
+                    "Mouse outside canvas bounds {{x: {}, y: {}}}",
+                    x, y
+                ));
                 self.previous_mouse_xy = None;
             }
         } else {
             self.previous_mouse_xy = None;
+            self.soft_erased.clear();
         }
 
-        let base_matrix = &mut self.base_matrix;
-        let modifier_matrix = &mut self.modifier_matrix;
+        if self.paused {
+            if !self.step_once {
+                return;
+            }
 
-        /*
-        paint in a bucket
-        spills into neighbouring cells
-        affecting their shade
-        */
-        for (index, spillover) in base_matrix
-            .iter_mut()
-            .enumerate()
-            .filter_map(|(index, value)| {
-                // for cells with paint, darken the cell, calculate spillover
-                if *value > DEFAULT_VALUE_CUTOFF {
-                    // cell spills over into its eight neighbours, so it gets divided into nine parts
-                    // that's eight parts for the neighbours, and one part to keep
-                    *value /= 9.0;
-
-                    // the current value will also be the amount that pours over into the neighbours
-                    return Some((index, *value));
+            self.step_once = false;
+        }
+
+        if self.noise_rate > 0.0 {
+            self.inject_noise();
+        }
+
+        // Nothing is going to change until something gets painted, so don't bother diffusing
+        if self.is_settled() && !mouse_buttons_are_held_down {
+            return;
+        }
+
+        let decay = self
+            .decay_mode
+            .per_tick(self.config.decay_factor, frame_time);
+        let config = self.config;
+        let (diffusion_mode, diffusion_kernel) = (self.diffusion_mode, self.diffusion_kernel);
+        let kernel = self
+            .kernel
+            .masked(self.neighbourhood)
+            .with_aspect(self.aspect);
+        let kernel = match self.retention {
+            Some(retention) => kernel.with_retention(retention),
+            None => kernel,
+        };
+        let spread = Spread {
+            kernel: &kernel,
+            conserve_mass: self.conserve_mass,
+            max_transfer: self.max_transfer,
+            sigma: self.sigma,
+        };
+        let decay_map = self.decay_map.as_ref();
+        // Most of the time nothing is frozen, and there's no need to check every cell's neighbours for frozen ones
+        let frozen = Some(&self.frozen).filter(|frozen| frozen.iter().any(|frozen| *frozen));
+        let diffuse_channel = |channel: &mut Matrix2D, back_matrix: &mut Matrix2D| match (
+            diffusion_mode,
+            diffusion_kernel,
+        ) {
+            (DiffusionMode::AntiDiffuse, _) => anti_diffuse(
+                channel,
+                back_matrix,
+                decay,
+                decay_map,
+                frozen,
+                &config,
+                &kernel,
+            ),
+            (DiffusionMode::Diffuse, DiffusionKernel::Uniform) => diffuse(
+                channel,
+                back_matrix,
+                decay,
+                decay_map,
+                frozen,
+                &config,
+                &spread,
+            ),
+            (DiffusionMode::Diffuse, DiffusionKernel::Gaussian) => gaussian_blur(
+                channel,
+                back_matrix,
+                decay,
+                decay_map,
+                frozen,
+                &config,
+                &spread,
+            ),
+        };
+
+        let change = match self.color_mode {
+            ColorMode::Grayscale => diffuse_channel(&mut self.base_matrix, &mut self.back_matrix),
+            ColorMode::Rgb => {
+                // Every channel is the same size, so they can all share one back buffer
+                let back_matrix = &mut self.back_matrix;
+                self.rgb_matrix
+                    .channels_mut()
+                    .map(|channel| diffuse_channel(channel, back_matrix))
+                    .sum()
+            }
+        };
+
+        if change < self.settle_epsilon {
+            self.ticks_without_change += 1;
+        } else {
+            self.ticks_without_change = 0;
+        }
+    }
+
+    /// Add `noise_amount` to a `noise_rate` fraction of the cells, picked at random. In RGB mode the same cells get
+    /// noise in every channel.
+    fn inject_noise(&mut self) {
+        let len = self.base_matrix.len();
+        let count = ((len as f32 * self.noise_rate.min(1.0)).round() as usize).min(len);
+        if count == 0 {
+            return;
+        }
+
+        let (amount, max_value) = (self.noise_amount, self.config.max_value);
+        let cells = index::sample(&mut self.rng, len, count);
+        let add_noise = |matrix: &mut Matrix2D| {
+            for index in cells.iter() {
+                if let Some(value) = matrix.get_mut(index) {
+                    *value = (*value + amount).clamp(0.0, max_value);
+                }
+            }
+        };
+
+        match self.color_mode {
+            ColorMode::Grayscale => add_noise(&mut self.base_matrix),
+            ColorMode::Rgb => self.rgb_matrix.channels_mut().for_each(add_noise),
+        }
+
+        self.unsettle();
+    }
+
+    /// Make decay vary across the canvas, with each cell of `decay_map` holding how much the decay of the cell under it
+    /// is multiplied by. The map has to be the same size as the canvas, and it's dropped if the canvas is resized.
+    /// Every multiplier has to be finite and not negative.
+    pub fn set_decay_map(&mut self, decay_map: Matrix2D) -> Result<(), app::Error> {
+        if decay_map.w() != self.window_rect.w() || decay_map.h() != self.window_rect.h() {
+            return Err(app::Error::CanvasSizeMismatch {
+                width: decay_map.w(),
+                height: decay_map.h(),
+                expected_width: self.window_rect.w(),
+                expected_height: self.window_rect.h(),
+            });
+        }
+
+        let invalid = decay_map
+            .iter()
+            .position(|multiplier| !multiplier.is_finite() || *multiplier < 0.0);
+        if let Some(index) = invalid {
+            let (x, y) = calculate_xy_from_index(index, decay_map.w());
+            return Err(app::Error::InvalidDecayMultiplier {
+                x,
+                y,
+                value: decay_map[(x, y)],
+            });
+        }
+
+        self.decay_map = Some(decay_map);
+        self.unsettle();
+
+        Ok(())
+    }
+
+    /// Go back to every cell decaying the same
+    pub fn clear_decay_map(&mut self) {
+        self.decay_map = None;
+        self.unsettle();
+    }
+
+    /// Whether the last `SETTLE_TICKS` ticks have barely changed the canvas. A settled simulation stops diffusing
+    /// until something is painted.
+    pub fn is_settled(&self) -> bool {
+        self.ticks_without_change >= SETTLE_TICKS
+    }
+
+    fn unsettle(&mut self) {
+        self.ticks_without_change = 0;
+    }
+
+    /// Call when a mouse button is pressed, before anything gets painted, so the stroke can be undone. Only the canvas
+    /// the current `color_mode` paints is snapshotted.
+    pub fn begin_stroke(&mut self) {
+        let (base_matrix, rgb_matrix) = (&self.base_matrix, &self.rgb_matrix);
+        let color_mode = self.color_mode;
+        self.history.begin_stroke_with(|| match color_mode {
+            ColorMode::Grayscale => Snapshot::Grayscale(base_matrix.clone()),
+            ColorMode::Rgb => Snapshot::Rgb(rgb_matrix.clone()),
+        });
+    }
+
+    /// Call once every mouse button has been released
+    pub fn end_stroke(&mut self) {
+        self.history.end_stroke();
+    }
+
+    pub fn undo(&mut self) -> bool {
+        self.unsettle();
+        let (base_matrix, rgb_matrix) = (&mut self.base_matrix, &mut self.rgb_matrix);
+        self.history
+            .undo_with(|snapshot| snapshot.restore(base_matrix, rgb_matrix))
+    }
+
+    pub fn redo(&mut self) -> bool {
+        self.unsettle();
+        let (base_matrix, rgb_matrix) = (&mut self.base_matrix, &mut self.rgb_matrix);
+        self.history
+            .redo_with(|snapshot| snapshot.restore(base_matrix, rgb_matrix))
+    }
+
+    /// Paint a filled circle of `brush_radius` cells centered on `{x, y}`. A radius of 1 paints a single cell.
+    /// Any part of the brush that falls outside of the canvas is skipped. Cells fully covered by the brush are set to
+    /// `value`, the feathered edge of a soft brush only ever adds ink, up to its share of `value`.
+    pub fn stamp_brush(&mut self, x: usize, y: usize, value: f32) {
+        let brush = brush_falloff(
+            x,
+            y,
+            self.brush_radius,
+            self.brush_hardness,
+            &self.window_rect,
+        );
+        for (brush_x, brush_y, weight) in brush {
+            let cell = &mut self.base_matrix[(brush_x, brush_y)];
+            *cell = if weight >= 1.0 {
+                value
+            } else {
+                cell.max(value * weight)
+            };
+        }
+    }
+
+    /// Erase a filled circle of `brush_radius` cells centered on `{x, y}`, the same shape `stamp_brush` paints. Cells
+    /// are emptied if `erase_hard` is set, otherwise they lose `erase_strength` without going below zero, once per
+    /// stroke.
+    pub fn erase_brush(&mut self, x: usize, y: usize) {
+        let canvas = self.window_rect;
+        for (brush_x, brush_y) in brush_cells(x, y, self.brush_radius, &canvas) {
+            if !self.erase_hard && !self.soft_erase_once(brush_x, brush_y) {
+                continue;
+            }
+
+            let value = &mut self.base_matrix[(brush_x, brush_y)];
+            *value = erased(*value, self.erase_hard, self.erase_strength);
+        }
+    }
+
+    /// Like `stamp_brush` but for the RGB canvas. Painting sets every channel to its share of `paint_color`, erasing
+    /// empties whichever channels `paint_color` uses, or softens them like `erase_brush` does.
+    pub fn stamp_brush_rgb(&mut self, x: usize, y: usize, erase: bool) {
+        let canvas = self.window_rect;
+        for (brush_x, brush_y) in brush_cells(x, y, self.brush_radius, &canvas) {
+            if erase && !self.erase_hard && !self.soft_erase_once(brush_x, brush_y) {
+                continue;
+            }
+
+            for (channel, share) in self.paint_color.iter().enumerate() {
+                let value = &mut self.rgb_matrix.channel_mut(channel)[(brush_x, brush_y)];
+                match (erase, *share > 0.0) {
+                    (false, _) => *value = share * self.config.max_value,
+                    (true, true) => *value = erased(*value, self.erase_hard, self.erase_strength),
+                    (true, false) => (),
                 }
+            }
+        }
+    }
 
-                if *value <= DEFAULT_VALUE_CUTOFF {
-                    // For values below the VALUE_CUTOFF, set them to zero in order to avoid ever-shrinking (but non-zero) float values
-                    *value = 0.0;
+    /// Whether the soft eraser should take ink out of the cell at `{x, y}`, which it only does the first time the
+    /// current stroke reaches it
+    fn soft_erase_once(&mut self, x: usize, y: usize) -> bool {
+        self.soft_erased
+            .insert(calculate_index_from_xy(x, y, self.window_rect.w()))
+    }
+
+    /// Add `amount` to every cell of a filled circle of `brush_radius` cells centered on `{x, y}`, without going over
+    /// `max_value`. It's the same shape `stamp_brush` paints.
+    pub fn accumulate_brush(&mut self, x: usize, y: usize, amount: f32) {
+        for (brush_x, brush_y) in brush_cells(x, y, self.brush_radius, &self.window_rect) {
+            let value = &mut self.base_matrix[(brush_x, brush_y)];
+            *value = (*value + amount).min(self.config.max_value);
+        }
+    }
+
+    /// Freeze or thaw a filled circle of `brush_radius` cells centered on `{x, y}`, the same shape `stamp_brush` paints
+    pub fn freeze_brush(&mut self, x: usize, y: usize, frozen: bool) {
+        for (brush_x, brush_y) in brush_cells(x, y, self.brush_radius, &self.window_rect) {
+            self.frozen[(brush_x, brush_y)] = frozen;
+        }
+    }
+
+    /// Paint or erase with the brush on whichever canvas the current `color_mode` uses, at `{x, y}` and wherever
+    /// `symmetry` mirrors it to. Accumulated paint flows for `frame_time` seconds. While `freezing` the brush freezes
+    /// cells instead, or thaws them when erasing.
+    fn paint(&mut self, x: usize, y: usize, erase: bool, frame_time: f32) {
+        self.unsettle();
+        let accumulated = self.paint_value * self.flow_rate * frame_time;
+        for (x, y) in self.symmetry.points(x, y, &self.window_rect) {
+            if self.freezing {
+                self.freeze_brush(x, y, !erase);
+                continue;
+            }
+
+            match self.color_mode {
+                ColorMode::Grayscale if erase => self.erase_brush(x, y),
+                ColorMode::Grayscale if self.accumulate_paint => {
+                    self.accumulate_brush(x, y, accumulated)
                 }
+                ColorMode::Grayscale => self.stamp_brush(x, y, self.paint_value),
+                ColorMode::Rgb => self.stamp_brush_rgb(x, y, erase),
+            }
+        }
+    }
 
-                // No spillover
-                None
-            })
-        {
-            // All neighbours are updated in the same way, so we define the closure once
-            // Spillover is added to the current value of each affected neighbour,
-            let spillover_fn = |value: &mut f32| *value += spillover;
+    /// Paint `value` at a position between cells, moving each of the four cells around `p` toward `value` by its
+    /// bilinear weight. A position exactly on a cell paints just that cell, and cells outside the canvas are skipped.
+    pub fn paint_subpixel(&mut self, p: Vector2<f32>, value: f32) {
+        let (left, top) = (p.x.floor(), p.y.floor());
+        let (fx, fy) = (p.x - left, p.y - top);
+        let corners = [
+            (0, 0, (1.0 - fx) * (1.0 - fy)),
+            (1, 0, fx * (1.0 - fy)),
+            (0, 1, (1.0 - fx) * fy),
+            (1, 1, fx * fy),
+        ];
+
+        self.unsettle();
+        for &(dx, dy, weight) in corners.iter() {
+            let (x, y) = (left as isize + dx, top as isize + dy);
+            if weight <= 0.0 || x < 0 || y < 0 || !self.window_rect.contains(x as usize, y as usize)
             {
-                use Direction::*;
-                for direction in &[
-                    NorthWest, North, NorthEast, West, East, SouthEast, South, SouthWest,
-                ] {
-                    // For each neighbouring cell in the modifier matrix, add the spillover value
-                    modifier_matrix
-                        .get_neighbouring_cell_mut(index, *direction)
-                        .map(spillover_fn);
-                }
+                continue;
             }
+
+            let cell = &mut self.base_matrix[(x as usize, y as usize)];
+            *cell += (value - *cell) * weight;
         }
+    }
 
-        // Apply the value of every cell in the modifier matrix to the corresponding cell in the base matrix
-        self.modifier_matrix
-            .iter_mut()
-            .enumerate()
-            .for_each(|(i, mod_value)| {
-                if let Some(value) = base_matrix.get_mut(i) {
-                    *value = (*value + *mod_value - (DEFAULT_DECAY_FACTOR * frame_time))
-                        .clamp(0.0, DEFAULT_MAX_VALUE);
+    /// Whether the brush is a single hard grayscale cell with nothing to mirror and paint that doesn't accumulate,
+    /// which is the only brush `paint_subpixel` can stand in for
+    fn paints_subpixel(&self, erase: bool) -> bool {
+        !erase
+            && !self.freezing
+            && !self.accumulate_paint
+            && self.brush_radius <= 1
+            && self.brush_hardness >= 1.0
+            && self.color_mode == ColorMode::Grayscale
+            && self.symmetry == Symmetry::None
+    }
+
+    /// Set every cell connected to `{x, y}` whose value is within `FLOOD_FILL_TOLERANCE` of the value at `{x, y}` to
+    /// `value`. Cells are connected through their north, south, east, and west neighbours only.
+    pub fn flood_fill(&mut self, x: usize, y: usize, value: f32) {
+        if !self.window_rect.contains(x, y) {
+            return;
+        }
+
+        self.unsettle();
+        let width = self.base_matrix.w();
+        let seed_value = self.base_matrix[(x, y)];
+        let mut visited = vec![false; self.base_matrix.len()];
+        let mut queue = VecDeque::new();
+        let mut cells_visited = 0;
+
+        visited[calculate_index_from_xy(x, y, width)] = true;
+        queue.push_back((x, y));
+
+        // A queue rather than recursion so big regions can't overflow the stack
+        while let Some((x, y)) = queue.pop_front() {
+            if cells_visited >= self.base_matrix.len() {
+                break;
+            }
+            cells_visited += 1;
+
+            self.base_matrix[(x, y)] = value;
+
+            let neighbours = [
+                (x.wrapping_sub(1), y),
+                (x + 1, y),
+                (x, y.wrapping_sub(1)),
+                (x, y + 1),
+            ];
+            for &(neighbour_x, neighbour_y) in &neighbours {
+                if !self.window_rect.contains(neighbour_x, neighbour_y) {
+                    continue;
+                }
+
+                let index = calculate_index_from_xy(neighbour_x, neighbour_y, width);
+                if visited[index]
+                    || (self.base_matrix[(neighbour_x, neighbour_y)] - seed_value).abs()
+                        > FLOOD_FILL_TOLERANCE
+                {
+                    continue;
                 }
 
-                // Reset each mod cells once we've used it up
-                *mod_value = 0.0;
+                visited[index] = true;
+                queue.push_back((neighbour_x, neighbour_y));
+            }
+        }
+
+        debug!(
+            "Flood filled {} cells from {{x: {}, y: {}}}",
+            cells_visited, x, y
+        );
+    }
+
+    /// Add `amount` to the cell of the grayscale canvas at `{x, y}`, without going over `max_value`. Unlike painting it
+    /// only touches the one cell, and nothing happens if that's outside of the canvas.
+    pub fn inject(&mut self, x: usize, y: usize, amount: f32) {
+        let max_value = self.config.max_value;
+        if let Some(value) = self.base_matrix.get_mut_v(Vector2::new(x, y)) {
+            *value = (*value + amount).clamp(0.0, max_value);
+            self.unsettle();
+        }
+    }
+
+    /// The value of the grayscale canvas at `{x, y}`, or `None` if that's outside of the canvas
+    pub fn sample(&self, x: usize, y: usize) -> Option<f32> {
+        self.base_matrix.get_v(Vector2::new(x, y)).copied()
+    }
+
+    /// How far the farthest cell of the grayscale canvas holding more than `threshold` is from `{cx, cy}`, in cells.
+    /// Handy for measuring how far a splash has spread. It's `0.0` if no cell holds more than `threshold`.
+    pub fn spread_radius(&self, cx: usize, cy: usize, threshold: f32) -> f32 {
+        self.base_matrix
+            .iter_xy()
+            .filter(|(_, _, value)| **value > threshold)
+            .map(|(x, y, _)| {
+                let (dx, dy) = (x as f32 - cx as f32, y as f32 - cy as f32);
+                (dx * dx + dy * dy).sqrt()
+            })
+            .fold(0.0, f32::max)
+    }
+
+    /// Log a message about painting, which happens every frame while the brush is held down. Every message is logged
+    /// at the trace level, but only one every `PAINT_LOG_INTERVAL_MS` at the debug level.
+    fn log_painting(&mut self, message: fmt::Arguments) {
+        if log_enabled!(Level::Trace) {
+            trace!("{}", message);
+        } else if log_enabled!(Level::Debug) && self.paint_log_throttle.ready(Instant::now()) {
+            debug!("{}", message);
+        }
+    }
+
+    /// Wipe every canvas clean and forget where the last stroke ended
+    pub fn clear(&mut self) {
+        self.base_matrix.clear();
+        self.back_matrix.clear();
+        self.rgb_matrix.clear();
+        self.previous_mouse_xy = None;
+        self.unsettle();
+    }
+
+    /// Flip the canvas, so empty cells are full of ink and full cells are empty
+    pub fn invert(&mut self) {
+        let max_value = self.config.max_value;
+        let invert = |value: f32| max_value - value;
+        match self.color_mode {
+            ColorMode::Grayscale => self.base_matrix.map_in_place(invert),
+            ColorMode::Rgb => self
+                .rgb_matrix
+                .channels_mut()
+                .for_each(|channel| channel.map_in_place(invert)),
+        }
+
+        self.unsettle();
+    }
+
+    /// Switch to the next color in `PAINT_COLORS`
+    pub fn cycle_paint_color(&mut self) {
+        let next = PAINT_COLORS
+            .iter()
+            .position(|color| *color == self.paint_color)
+            .map_or(0, |index| (index + 1) % PAINT_COLORS.len());
+
+        self.paint_color = PAINT_COLORS[next];
+    }
+
+    /// Draw a pattern onto the grayscale canvas, capped at `max_value`. Random patterns use the model's random number
+    /// generator.
+    pub fn seed_pattern(&mut self, pattern: Pattern) {
+        pattern.apply(&mut self.base_matrix, &mut self.rng);
+        let max_value = self.config.max_value;
+        self.base_matrix.map_in_place(|value| value.min(max_value));
+        self.unsettle();
+    }
+
+    /// Replace the grayscale canvas with an image, capped at `max_value`. Images larger than the canvas are cropped to
+    /// its top-left corner, smaller ones are padded with blank cells along the right and bottom.
+    pub fn load_image(&mut self, path: &Path) -> Result<(), app::Error> {
+        let mut matrix = Matrix2D::from_image(path)?;
+        matrix.resize(self.window_rect.h(), self.window_rect.w());
+        let max_value = self.config.max_value;
+        matrix.map_in_place(|value| value.min(max_value));
+        self.base_matrix = matrix;
+        self.unsettle();
+
+        Ok(())
+    }
+
+    /// Load an image as the background, which is drawn behind the grayscale canvas but never diffuses. It's fitted to
+    /// the canvas the same way `load_image` fits images.
+    pub fn load_background(&mut self, path: &Path) -> Result<(), app::Error> {
+        let mut matrix = Matrix2D::from_image(path)?;
+        matrix.resize(self.window_rect.h(), self.window_rect.w());
+        let max_value = self.config.max_value;
+        matrix.map_in_place(|value| value.min(max_value));
+        self.background = Some(matrix);
+
+        Ok(())
+    }
+
+    /// Write the canvas to `path` as JSON so the simulation can be picked up again later with `load_state`
+    #[cfg(feature = "serde")]
+    pub fn save_state(&self, path: &Path) -> Result<(), app::Error> {
+        let state = SavedStateRef {
+            base_matrix: &self.base_matrix,
+        };
+        let file = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(file, &state)?;
+
+        Ok(())
+    }
+
+    /// Restore the canvas from a file written by `save_state`. The saved canvas must be the same size as this one.
+    #[cfg(feature = "serde")]
+    pub fn load_state(&mut self, path: &Path) -> Result<(), app::Error> {
+        let file = BufReader::new(File::open(path)?);
+        let state: SavedState = serde_json::from_reader(file)?;
+
+        let matrix = state.base_matrix;
+        if matrix.w() != self.window_rect.w() || matrix.h() != self.window_rect.h() {
+            return Err(app::Error::CanvasSizeMismatch {
+                width: matrix.w(),
+                height: matrix.h(),
+                expected_width: self.window_rect.w(),
+                expected_height: self.window_rect.h(),
+            });
+        }
+
+        self.base_matrix = matrix;
+        self.unsettle();
+
+        Ok(())
+    }
+
+    /// Map a position in the pixel buffer to a position on the canvas, undoing the zoom and pan. Positions are measured
+    /// from the center of a cell, so rounding one picks the cell drawn under that pixel.
+    pub fn buffer_to_canvas(&self, buffer_xy: Vector2<f32>) -> Vector2<f32> {
+        let pixels_per_cell = self.pixels_per_cell() as f32;
+        let (pan_x, pan_y) = self.pan_cells();
+        Vector2::new(
+            (buffer_xy.x + 0.5) / pixels_per_cell - 0.5 + pan_x as f32,
+            (buffer_xy.y + 0.5) / pixels_per_cell - 0.5 + pan_y as f32,
+        )
+    }
+
+    /// How many pixels of the frame each cell is drawn across, counting both `sim_scale` and `zoom`
+    pub fn pixels_per_cell(&self) -> usize {
+        self.sim_scale.max(1) * self.zoom.clamp(MIN_ZOOM, MAX_ZOOM)
+    }
+
+    /// The `(width, height)` of the frame `draw` fills, which is the canvas scaled up by `sim_scale`
+    pub fn frame_size(&self) -> (usize, usize) {
+        let sim_scale = self.sim_scale.max(1);
+        (
+            self.base_matrix.w() * sim_scale,
+            self.base_matrix.h() * sim_scale,
+        )
+    }
+
+    /// Whether `frame` has room for exactly the `frame_size` pixels `draw` fills
+    fn check_frame_size(&self, frame: &[u8]) -> Result<(), app::Error> {
+        let (width, height) = self.frame_size();
+        if frame.len() != width * height * 4 {
+            return Err(app::Error::SizeMismatch {
+                frame_pixels: frame.len() / 4,
+                matrix_len: width * height,
             });
+        }
+
+        Ok(())
+    }
+
+    /// Change the zoom, keeping the pan offset within the canvas at the new zoom
+    pub fn set_zoom(&mut self, zoom: usize) {
+        self.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        self.pan_by(Vector2::new(0.0, 0.0));
+    }
+
+    /// Move the view by `delta` cells, stopping once its edge reaches the edge of the canvas
+    pub fn pan_by(&mut self, delta: Vector2<f32>) {
+        let (max_x, max_y) = self.max_pan();
+        let pan_offset = self.pan_offset + delta;
+        self.pan_offset = Vector2::new(
+            pan_offset.x.clamp(0.0, max_x as f32),
+            pan_offset.y.clamp(0.0, max_y as f32),
+        );
+    }
+
+    /// The furthest the view can pan at the current zoom, which is as far as it goes while its bottom-right pixel still
+    /// lands on the canvas
+    fn max_pan(&self) -> (usize, usize) {
+        let zoom = self.zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        let (last_x, last_y) = (
+            self.window_rect.w().saturating_sub(1),
+            self.window_rect.h().saturating_sub(1),
+        );
+
+        (last_x - last_x / zoom, last_y - last_y / zoom)
+    }
+
+    /// The pan offset in whole cells, clamped to the canvas in case `pan_offset` was set directly
+    fn pan_cells(&self) -> (usize, usize) {
+        let (max_x, max_y) = self.max_pan();
+        (
+            (self.pan_offset.x.max(0.0).round() as usize).min(max_x),
+            (self.pan_offset.y.max(0.0).round() as usize).min(max_y),
+        )
+    }
+
+    /// Save the canvas as an RGBA PNG, exactly as it would be drawn to the screen
+    pub fn save_png(&self, path: &Path) -> Result<(), app::Error> {
+        if self.base_matrix.is_empty() {
+            return Err(app::Error::EmptyCanvas);
+        }
+
+        let (width, height) = self.frame_size();
+        let mut frame = vec![0; width * height * 4];
+        self.draw(&mut frame)?;
+
+        image::save_buffer(path, &frame, width as u32, height as u32, ColorType::Rgba8)?;
+
+        Ok(())
     }
 
     /// Draw the `World` state to the frame buffer.
     ///
-    /// Assumes the default texture format: `wgpu::TextureFormat::Rgba8UnormSrgb`
-    pub fn draw(&self, frame: &mut [u8]) {
-        assert_eq!(frame.len() / 4, self.base_matrix.len());
+    /// Assumes the default texture format: `wgpu::TextureFormat::Rgba8UnormSrgb`. Fails without drawing anything if the
+    /// frame isn't `frame_size` pixels, which is one pixel per cell unless `sim_scale` is set.
+    pub fn draw(&self, frame: &mut [u8]) -> Result<(), app::Error> {
+        self.check_frame_size(frame)?;
+
+        let gamma = self.gamma.clamp(MIN_GAMMA, MAX_GAMMA);
+        let render_mode = self.render_mode;
+        let pixels_per_cell = self.pixels_per_cell();
+        let width = self.base_matrix.w();
+        let (frame_width, _) = self.frame_size();
+        let (pan_x, pan_y) = self.pan_cells();
+        let grid_spacing = if self.show_grid { self.grid_spacing } else { 0 };
 
         frame
             .par_chunks_mut(4)
             .enumerate()
-            .for_each(|(index, pixel)| {
-                let value = *self
-                    .base_matrix
-                    .get(index)
-                    .ok_or_else(|| app::Error::InvalidIndex {
-                        list_name: "base_matrix".to_owned(),
-                        index,
-                        len: self.base_matrix.len(),
-                    })
-                    .unwrap();
-                let value = (value.min(1.0) * 255.0).round();
-                let value = (255.0 - value).clamp(0.0, 255.0) as u8;
+            .for_each(|(pixel_index, pixel)| {
+                // Each cell is drawn as a square of pixels_per_cell pixels on a side, the nearest cell fills each
+                // pixel, starting from the cell the view is panned to
+                let (x, y) = calculate_xy_from_index(pixel_index, frame_width);
+                let index = calculate_index_from_xy(
+                    x / pixels_per_cell + pan_x,
+                    y / pixels_per_cell + pan_y,
+                    width,
+                );
 
-                pixel.copy_from_slice(&[value, value, value, 0xff]);
-            })
+                let [r, g, b, a] = match self.color_mode {
+                    ColorMode::Rgb => {
+                        let rgb = self
+                            .rgb_matrix
+                            .get(index)
+                            .ok_or_else(|| app::Error::InvalidIndex {
+                                list_name: "rgb_matrix".to_owned(),
+                                index,
+                                len: self.rgb_matrix.len(),
+                            })
+                            .unwrap();
+                        let [r, g, b] = rgb.map(|value| {
+                            (render_mode.apply(gamma_correct(value, gamma)) * 255.0)
+                                .round()
+                                .clamp(0.0, 255.0) as u8
+                        });
+
+                        [r, g, b, 0xff]
+                    }
+                    ColorMode::Grayscale => {
+                        let value = *self
+                            .base_matrix
+                            .get(index)
+                            .ok_or_else(|| app::Error::InvalidIndex {
+                                list_name: "base_matrix".to_owned(),
+                                index,
+                                len: self.base_matrix.len(),
+                            })
+                            .unwrap();
+                        let value = match self
+                            .background
+                            .as_ref()
+                            .and_then(|background| background.get(index))
+                        {
+                            Some(background) => self.background_blend.apply(*background, value),
+                            None => value,
+                        };
+
+                        self.palette
+                            .color(render_mode.apply(gamma_correct(value, gamma)))
+                    }
+                };
+
+                let [r, g, b, a] = if self.invert_display {
+                    [255 - r, 255 - g, 255 - b, a]
+                } else {
+                    [r, g, b, a]
+                };
+
+                // Grid lines run along the first row and column of pixels of every grid_spacing-th cell
+                let on_grid_line = |pixel: usize, pan: usize| {
+                    pixel.is_multiple_of(pixels_per_cell)
+                        && (pixel / pixels_per_cell + pan).is_multiple_of(grid_spacing)
+                };
+                if grid_spacing > 0 && (on_grid_line(x, pan_x) || on_grid_line(y, pan_y)) {
+                    let [r, g, b] = tint_with_grid([r, g, b]);
+                    pixel.copy_from_slice(&[r, g, b, a]);
+                } else {
+                    pixel.copy_from_slice(&[r, g, b, a]);
+                }
+            });
+
+        Ok(())
+    }
+
+    /// Write the frame rate, brush radius, paint value, and palette into the top-left corner of a frame that `draw`
+    /// has drawn into. Fails without drawing anything if the frame isn't `frame_size` pixels.
+    pub fn draw_hud(&self, frame: &mut [u8]) -> Result<(), app::Error> {
+        self.check_frame_size(frame)?;
+
+        hud::draw_lines(frame, self.frame_size().0, &self.hud_lines());
+
+        Ok(())
+    }
+
+    fn hud_lines(&self) -> Vec<String> {
+        vec![
+            format!("FPS {}", self.fps.trunc()),
+            format!("BRUSH {}", self.brush_radius),
+            format!("VALUE {}", self.paint_value.round()),
+            format!("PALETTE {:?}", self.palette),
+        ]
+    }
+}
+
+/// What's left of a cell holding `value` after the eraser passes over it
+fn erased(value: f32, erase_hard: bool, erase_strength: f32) -> f32 {
+    if erase_hard {
+        0.0
+    } else {
+        (value - erase_strength).max(0.0)
+    }
+}
+
+/// Mix a pixel with `GRID_COLOR`, `GRID_OPACITY` of the way towards it
+fn tint_with_grid(rgb: [u8; 3]) -> [u8; 3] {
+    let mut tinted = rgb;
+    for (channel, grid) in tinted.iter_mut().zip(GRID_COLOR.iter()) {
+        *channel =
+            (*channel as f32 + (*grid as f32 - *channel as f32) * GRID_OPACITY).round() as u8;
+    }
+
+    tinted
+}
+
+/// Clamp `value` to `[0, 1]`, then raise it to the power of `1.0 / gamma`. Clamping first keeps negative values from
+/// turning into NaN.
+fn gamma_correct(value: f32, gamma: f32) -> f32 {
+    value.clamp(0.0, 1.0).powf(1.0 / gamma)
+}
+
+/// The coordinates of every cell covered by a brush of `radius` centered on `{x, y}` that lie within `canvas`
+fn brush_cells(
+    x: usize,
+    y: usize,
+    radius: usize,
+    canvas: &Rect<usize>,
+) -> impl Iterator<Item = (usize, usize)> + '_ {
+    // The center cell counts towards the radius, so the brush reaches `radius - 1` cells out from it
+    let reach = radius.max(1) as isize - 1;
+    let (x, y) = (x as isize, y as isize);
+
+    (-reach..=reach)
+        .flat_map(move |dy| (-reach..=reach).map(move |dx| (dx, dy)))
+        .filter(move |(dx, dy)| dx * dx + dy * dy <= reach * reach)
+        .map(move |(dx, dy)| (x + dx, y + dy))
+        .filter(|(brush_x, brush_y)| *brush_x >= 0 && *brush_y >= 0)
+        .map(|(brush_x, brush_y)| (brush_x as usize, brush_y as usize))
+        .filter(move |(brush_x, brush_y)| canvas.contains(*brush_x, *brush_y))
+}
+
+/// Like `brush_cells`, but with how much of the brush's ink each cell gets. Cells `brush_cells` covers get all of it.
+/// As `hardness` drops from 1 to 0 the edge feathers out past them over a band up to `radius` cells wide.
+fn brush_falloff(
+    x: usize,
+    y: usize,
+    radius: usize,
+    hardness: f32,
+    canvas: &Rect<usize>,
+) -> impl Iterator<Item = (usize, usize, f32)> + '_ {
+    let radius = radius.max(1) as f32;
+    let edge = radius - 1.0;
+    let feather = (1.0 - hardness.clamp(0.0, 1.0)) * radius;
+    let reach = (edge + feather).ceil() as isize;
+    let (x, y) = (x as isize, y as isize);
+
+    (-reach..=reach)
+        .flat_map(move |dy| (-reach..=reach).map(move |dx| (dx, dy)))
+        .map(move |(dx, dy)| {
+            let distance = (dx as f32).hypot(dy as f32);
+            let weight = if feather > 0.0 {
+                ((edge - distance) / feather + 1.0).clamp(0.0, 1.0)
+            } else if distance <= edge {
+                1.0
+            } else {
+                0.0
+            };
+
+            (x + dx, y + dy, weight)
+        })
+        .filter(|(brush_x, brush_y, weight)| *brush_x >= 0 && *brush_y >= 0 && *weight > 0.0)
+        .map(|(brush_x, brush_y, weight)| (brush_x as usize, brush_y as usize, weight))
+        .filter(move |(brush_x, brush_y, _)| canvas.contains(*brush_x, *brush_y))
+}
+
+/// How many times as strongly the cell at `index` decays, whatever `decay_map` holds for it
+fn decay_multiplier(decay_map: Option<&Matrix2D>, index: usize) -> f32 {
+    decay_map
+        .and_then(|decay_map| decay_map.get(index))
+        .copied()
+        .unwrap_or(1.0)
+}
+
+/// How `diffuse` and `gaussian_blur` share a cell's ink out between its neighbours
+struct Spread<'a> {
+    kernel: &'a Kernel,
+    /// Keep the spillover meant for neighbours that are off the edge of the canvas
+    conserve_mass: bool,
+    /// The most a cell sends any one neighbour, see `Model::max_transfer`
+    max_transfer: Option<f32>,
+    /// The standard deviation of `gaussian_blur`, see `Model::sigma`
+    sigma: f32,
+}
+
+impl Spread<'_> {
+    /// How much of `amount` actually makes it to a neighbour once it's capped at `max_transfer`
+    fn send(&self, amount: f32) -> f32 {
+        match self.max_transfer {
+            Some(max_transfer) => amount.min(max_transfer.max(0.0)),
+            None => amount,
+        }
+    }
+}
+
+/// Run one tick of diffusion on a single channel, then decay every cell, scaled by `decay_map` if there is one. The
+/// next tick is written into `back_matrix`, which is then swapped with `base_matrix`, so whatever `back_matrix` held
+/// going in is overwritten. Returns the total absolute change to the channel.
+///
+/// Cells along the edge of a clamped canvas are missing some of their neighbours. Normally the spillover meant for the
+/// missing neighbours is lost, with `conserve_mass` the cell keeps it instead.
+///
+/// Cells set in `frozen` neither send nor receive any ink, and don't decay. Their neighbours keep whatever they would
+/// have sent them.
+fn diffuse(
+    base_matrix: &mut Matrix2D,
+    back_matrix: &mut Matrix2D,
+    decay: Decay,
+    decay_map: Option<&Matrix2D>,
+    frozen: Option<&Matrix2D<bool>>,
+    config: &SimulationConfig,
+    spread: &Spread,
+) -> f32 {
+    let Spread {
+        kernel,
+        conserve_mass,
+        max_transfer,
+        ..
+    } = *spread;
+
+    /*
+    paint in a bucket
+    spills into neighbouring cells
+    affecting their shade
+    */
+    let parts = kernel.total();
+    let spillover = |value: f32| {
+        if value > config.value_cutoff {
+            // cell spills over into its neighbours, so it gets divided into as many parts as the kernel's weights add
+            // up to. With the uniform kernel and eight neighbours that's nine parts: eight parts for the neighbours,
+            // and one part to keep. With four neighbours it gets divided into five.
+            value / parts
+        } else {
+            // For values below the VALUE_CUTOFF, set them to zero in order to avoid ever-shrinking (but non-zero) float values
+            0.0
+        }
+    };
+
+    // Rather than each cell scattering its spillover into its neighbours, each cell gathers the spillover from its
+    // neighbours. Every cell only writes to itself, so this can run in parallel without any data races.
+    let current = &*base_matrix;
+    let is_frozen = |index: usize| is_frozen(frozen, index);
+    let gather = |index: usize| {
+        let value = current.get(index).copied().unwrap_or_default();
+        if is_frozen(index) {
+            return value;
+        }
+
+        let kept = spillover(value);
+
+        // A neighbour sends this cell the parts it sends in the opposite direction to the one it lies in
+        let mut gathered = current
+            .neighbour_indices(index)
+            .filter(|(direction, neighbour)| {
+                kernel.weight(direction.opposite()) > 0.0 && !is_frozen(*neighbour)
+            })
+            .fold(0.0, |sum, (direction, neighbour)| {
+                let neighbour = current.get(neighbour).copied().unwrap_or_default();
+                sum + spread.send(spillover(neighbour) * kernel.weight(direction.opposite()))
+            });
+
+        // Whatever would have been sent to frozen neighbours stays put
+        let (frozen_parts, sent_to_frozen) = if frozen.is_some() {
+            current
+                .neighbour_indices(index)
+                .filter(|(_, neighbour)| is_frozen(*neighbour))
+                .fold((0.0, 0.0), |(parts, sent), (direction, _)| {
+                    let weight = kernel.weight(direction);
+                    (parts + weight, sent + spread.send(kept * weight))
+                })
+        } else {
+            (0.0, 0.0)
+        };
+
+        if max_transfer.is_some() {
+            // The cell keeps everything it doesn't manage to send. Without conserve_mass, what it sends towards
+            // missing neighbours is lost just the same.
+            let sent: f32 = if conserve_mass {
+                current
+                    .neighbour_indices(index)
+                    .map(|(direction, _)| spread.send(kept * kernel.weight(direction)))
+                    .sum()
+            } else {
+                Direction::ALL
+                    .iter()
+                    .map(|direction| spread.send(kept * kernel.weight(*direction)))
+                    .sum()
+            };
+
+            return kept * parts - (sent - sent_to_frozen) + gathered;
+        }
+
+        if conserve_mass {
+            let sent_parts: f32 = current
+                .neighbour_indices(index)
+                .map(|(direction, _)| kernel.weight(direction))
+                .sum();
+            let missing_parts = parts - kernel.center() - sent_parts;
+
+            gathered += kept * missing_parts;
+        }
+
+        kept * (kernel.center() + frozen_parts) + gathered
+    };
+
+    // Each chunk of cells is decayed as soon as it's gathered, which is done over plain slices so it can use SIMD
+    let change = decay::fill_decay_and_clamp(
+        back_matrix.as_mut_slice(),
+        current.as_slice(),
+        decay,
+        decay_map.map(Matrix2D::as_slice),
+        config.max_value,
+        |start, next| {
+            for (offset, next_value) in next.iter_mut().enumerate() {
+                *next_value = gather(start + offset);
+            }
+        },
+    ) - restore_frozen(back_matrix, current, frozen);
+
+    std::mem::swap(base_matrix, back_matrix);
+
+    change
+}
+
+/// Whether the cell at `index` is set in `frozen`, if anything is frozen at all
+fn is_frozen(frozen: Option<&Matrix2D<bool>>, index: usize) -> bool {
+    frozen.is_some_and(|frozen| frozen.get(index).copied().unwrap_or_default())
+}
+
+/// Put every frozen cell of `next` back to how it was in `current`, undoing its decay, and return how much of the
+/// change to the channel that takes back
+fn restore_frozen(next: &mut Matrix2D, current: &Matrix2D, frozen: Option<&Matrix2D<bool>>) -> f32 {
+    let frozen = match frozen {
+        Some(frozen) => frozen,
+        None => return 0.0,
+    };
+
+    next.par_iter_mut()
+        .zip(current.as_slice().par_iter())
+        .zip(frozen.as_slice().par_iter())
+        .filter(|(_, frozen)| **frozen)
+        .map(|((next, current), _)| {
+            let restored = (*next - current).abs();
+            *next = *current;
+            restored
+        })
+        .sum()
+}
+
+/// Sharpen a single channel by adding `ANTI_DIFFUSION_STRENGTH` times the difference between each cell and the
+/// average of its neighbours, weighed by `kernel`, then decay it like `diffuse` does. Cells are clamped to
+/// `[0, max_value]` every tick, which is all that keeps edges from growing without bound. Like `diffuse`, the next
+/// tick is written into `back_matrix` and swapped with `base_matrix`, and the total absolute change is returned. Cells
+/// that end up at or below `value_cutoff` are emptied, and cells set in `frozen` are left as they are.
+fn anti_diffuse(
+    base_matrix: &mut Matrix2D,
+    back_matrix: &mut Matrix2D,
+    decay: Decay,
+    decay_map: Option<&Matrix2D>,
+    frozen: Option<&Matrix2D<bool>>,
+    config: &SimulationConfig,
+    kernel: &Kernel,
+) -> f32 {
+    let current = &*base_matrix;
+    back_matrix
+        .par_iter_mut()
+        .enumerate()
+        .for_each(|(index, next_value)| {
+            let value = current.get(index).copied().unwrap_or_default();
+            let (weighted_sum, total_weight) = current
+                .neighbours(index)
+                .map(|(direction, neighbour)| (*neighbour, kernel.weight(direction)))
+                .filter(|(_, weight)| *weight > 0.0)
+                .fold((0.0, 0.0), |(sum, total), (neighbour, weight)| {
+                    (sum + neighbour * weight, total + weight)
+                });
+
+            let sharpened = if total_weight > 0.0 {
+                let average = weighted_sum / total_weight;
+                value + ANTI_DIFFUSION_STRENGTH * (value - average)
+            } else {
+                value
+            };
+            // Faint cells are emptied, just like diffusion does, so sharpening doesn't leave them around forever
+            *next_value = if sharpened > config.value_cutoff {
+                sharpened
+            } else {
+                0.0
+            };
+        });
+
+    let change = decay::decay_and_clamp(
+        back_matrix.as_mut_slice(),
+        current.as_slice(),
+        decay,
+        decay_map.map(Matrix2D::as_slice),
+        config.max_value,
+    ) - restore_frozen(back_matrix, current, frozen);
+
+    std::mem::swap(base_matrix, back_matrix);
+
+    change
+}
+
+/// Blur a single channel with a Gaussian of standard deviation `spread.sigma`, then decay every cell like `diffuse`
+/// does. The blur is separable, so it's done as a horizontal pass into `back_matrix` followed by a vertical pass back
+/// into `base_matrix`. Like `diffuse`, whatever `back_matrix` held going in is overwritten, and the total absolute
+/// change to the channel is returned. Also like `diffuse`, cells set in `frozen` neither send nor receive any ink, and
+/// with `spread.conserve_mass` no ink is blurred off the edge of the canvas.
+fn gaussian_blur(
+    base_matrix: &mut Matrix2D,
+    back_matrix: &mut Matrix2D,
+    decay: Decay,
+    decay_map: Option<&Matrix2D>,
+    frozen: Option<&Matrix2D<bool>>,
+    config: &SimulationConfig,
+    spread: &Spread,
+) -> f32 {
+    let Spread {
+        conserve_mass,
+        sigma,
+        ..
+    } = *spread;
+    let weights = gaussian_weights(sigma);
+    let radius = (weights.len() / 2) as isize;
+    let (width, height) = (base_matrix.w(), base_matrix.h());
+    let wrap = base_matrix.boundary_mode() == BoundaryMode::Wrap;
+
+    // The part of a blur that would land outside of the canvas is lost, unless the canvas wraps around
+    let tap = move |coordinate: usize, offset: isize, len: usize| -> Option<usize> {
+        let tapped = coordinate as isize + offset;
+        if wrap {
+            Some(tapped.rem_euclid(len as isize) as usize)
+        } else if tapped >= 0 && tapped < len as isize {
+            Some(tapped as usize)
+        } else {
+            None
+        }
+    };
+
+    // With conserve_mass, each cell spreads all of its ink over the part of its blur that lands on the canvas, by
+    // dividing every weight it sends with by how much of the blur that is. The weights are symmetric, so that's the
+    // same as the weights of the taps it gathers from that are on the canvas.
+    let shares = |len: usize| -> Vec<f32> {
+        (0..len)
+            .map(|coordinate| {
+                if !conserve_mass || wrap {
+                    return 1.0;
+                }
+
+                weights
+                    .iter()
+                    .zip(-radius..=radius)
+                    .filter(|(_, offset)| tap(coordinate, *offset, len).is_some())
+                    .map(|(weight, _)| weight)
+                    .sum()
+            })
+            .collect()
+    };
+    let (shares_x, shares_y) = (shares(width), shares(height));
+    // What the cell at `coordinate` is sent with from the cell at `tapped`. A frozen cell sends back what it would
+    // have been sent, so that's divided by the cell's own share instead.
+    let share = |weight: f32, coordinate: usize, tapped: usize, index: usize, shares: &[f32]| {
+        if is_frozen(frozen, index) {
+            weight / shares[coordinate]
+        } else {
+            weight / shares[tapped]
+        }
+    };
+
+    let source = &*base_matrix;
+    back_matrix
+        .par_iter_mut()
+        .enumerate()
+        .for_each(|(index, blurred)| {
+            let (x, y) = calculate_xy_from_index(index, width);
+            let taps = weights
+                .iter()
+                .zip(-radius..=radius)
+                .filter_map(|(weight, offset)| {
+                    tap(x, offset, width).map(|tapped_x| {
+                        let tapped = calculate_index_from_xy(tapped_x, y, width);
+                        (share(*weight, x, tapped_x, tapped, &shares_x), tapped)
+                    })
+                });
+            *blurred = blur_cell(source, index, taps, frozen);
+        });
+
+    let source = &*back_matrix;
+    base_matrix
+        .par_iter_mut()
+        .enumerate()
+        .map(|(index, value)| {
+            if is_frozen(frozen, index) {
+                return 0.0;
+            }
+
+            let (x, y) = calculate_xy_from_index(index, width);
+            let taps = weights
+                .iter()
+                .zip(-radius..=radius)
+                .filter_map(|(weight, offset)| {
+                    tap(y, offset, height).map(|tapped_y| {
+                        let tapped = calculate_index_from_xy(x, tapped_y, width);
+                        (share(*weight, y, tapped_y, tapped, &shares_y), tapped)
+                    })
+                });
+            let blurred = blur_cell(source, index, taps, frozen);
+
+            let previous_value = *value;
+            *value = if blurred > config.value_cutoff {
+                decay
+                    .apply(blurred, decay_multiplier(decay_map, index))
+                    .clamp(0.0, config.max_value)
+            } else {
+                0.0
+            };
+
+            (*value - previous_value).abs()
+        })
+        .sum()
+}
+
+/// One pass of `gaussian_blur` for the cell at `index`, the sum of each tapped cell times its weight. A cell keeps the
+/// part it would have sent to a frozen cell and gets nothing from one, while a frozen cell keeps its value.
+fn blur_cell(
+    source: &Matrix2D,
+    index: usize,
+    taps: impl Iterator<Item = (f32, usize)>,
+    frozen: Option<&Matrix2D<bool>>,
+) -> f32 {
+    let value = source.get(index).copied().unwrap_or_default();
+    if is_frozen(frozen, index) {
+        return value;
+    }
+
+    taps.map(|(weight, tapped)| {
+        if is_frozen(frozen, tapped) {
+            weight * value
+        } else {
+            weight * source.get(tapped).copied().unwrap_or_default()
+        }
+    })
+    .sum()
+}
+
+/// A normalized 1D Gaussian kernel that extends three standard deviations to either side of its center
+fn gaussian_weights(sigma: f32) -> Vec<f32> {
+    let sigma = sigma.max(f32::EPSILON);
+    let radius = (3.0 * sigma).ceil().max(1.0) as isize;
+    let weights: Vec<f32> = (-radius..=radius)
+        .map(|offset| (-(offset * offset) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let total: f32 = weights.iter().sum();
+
+    weights.into_iter().map(|weight| weight / total).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::constants::{DEFAULT_DECAY_FACTOR, DEFAULT_MAX_VALUE, DEFAULT_VALUE_CUTOFF};
+    use std::sync::{Arc, Mutex};
+
+    /// The original single-threaded update: each cell scatters its spillover into its neighbours
+    fn serial_update(base_matrix: &mut Matrix2D, modifier_matrix: &mut Matrix2D, frame_time: f32) {
+        for index in 0..base_matrix.len() {
+            let value = base_matrix.get_mut(index).unwrap();
+            if *value <= DEFAULT_VALUE_CUTOFF {
+                *value = 0.0;
+                continue;
+            }
+
+            *value /= 9.0;
+            let spillover = *value;
+            for direction in &Direction::ALL {
+                if let Some(neighbour) =
+                    modifier_matrix.get_neighbouring_cell_mut(index, *direction)
+                {
+                    *neighbour += spillover;
+                }
+            }
+        }
+
+        for index in 0..base_matrix.len() {
+            let mod_value = modifier_matrix.get_mut(index).unwrap();
+            let value = base_matrix.get_mut(index).unwrap();
+            *value = (*value + *mod_value - (DEFAULT_DECAY_FACTOR * frame_time))
+                .clamp(0.0, DEFAULT_MAX_VALUE);
+            *mod_value = 0.0;
+        }
+    }
+
+    /// The original two pass update: divide every cell in place, gather the spillover into a modifier matrix, then add
+    /// the modifier matrix back in and zero it
+    fn two_pass_update(
+        base_matrix: &mut Matrix2D,
+        modifier_matrix: &mut Matrix2D,
+        decay: f32,
+        kernel: &Kernel,
+    ) {
+        let parts = kernel.total();
+        for value in base_matrix.iter_mut() {
+            *value = if *value > DEFAULT_VALUE_CUTOFF {
+                *value / parts
+            } else {
+                0.0
+            };
+        }
+
+        for index in 0..base_matrix.len() {
+            let sent_parts: f32 = base_matrix
+                .neighbour_indices(index)
+                .map(|(direction, _)| kernel.weight(direction))
+                .sum();
+            let gathered = base_matrix
+                .neighbours(index)
+                .fold(0.0, |sum, (direction, spillover)| {
+                    sum + spillover * kernel.weight(direction.opposite())
+                });
+            let missing_parts = parts - kernel.center() - sent_parts;
+            modifier_matrix[calculate_xy_from_index(index, base_matrix.w())] =
+                gathered + base_matrix.get(index).unwrap() * missing_parts;
+        }
+
+        for (value, mod_value) in base_matrix.iter_mut().zip(modifier_matrix.iter_mut()) {
+            *value = (*value * kernel.center() + *mod_value - decay).clamp(0.0, DEFAULT_MAX_VALUE);
+            *mod_value = 0.0;
+        }
+    }
+
+    #[test]
+    fn test_swapped_buffers_match_two_pass_update() {
+        let (height, width) = (12, 16);
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(height, width)));
+        model.conserve_mass = true;
+        model.kernel = Kernel::gravity(1.5);
+        let mut expected: Matrix2D = Matrix2D::new(height, width);
+        let mut expected_modifier: Matrix2D = Matrix2D::new(height, width);
+
+        for (index, (value, expected_value)) in model
+            .base_matrix
+            .iter_mut()
+            .zip(expected.iter_mut())
+            .enumerate()
+        {
+            let seeded = if index % 5 == 0 {
+                DEFAULT_MAX_VALUE
+            } else {
+                0.0
+            };
+            *value = seeded;
+            *expected_value = seeded;
+        }
+
+        let decay = DEFAULT_DECAY_FACTOR * 0.016;
+        for _ in 0..8 {
+            model.update(0.016);
+            two_pass_update(&mut expected, &mut expected_modifier, decay, &model.kernel);
+            assert_eq!(expected.as_slice(), model.base_matrix.as_slice());
+        }
+    }
+
+    #[test]
+    fn test_parallel_update_matches_serial_update() {
+        let (height, width) = (24, 32);
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(height, width)));
+        let mut expected = Matrix2D::new(height, width);
+        let mut expected_modifier = Matrix2D::new(height, width);
+
+        for (index, (value, expected_value)) in model
+            .base_matrix
+            .iter_mut()
+            .zip(expected.iter_mut())
+            .enumerate()
+        {
+            let seeded = if index % 7 == 0 {
+                DEFAULT_MAX_VALUE
+            } else {
+                (index % 13) as f32 * 0.1
+            };
+            *value = seeded;
+            *expected_value = seeded;
+        }
+
+        for _ in 0..10 {
+            model.update(0.016);
+            serial_update(&mut expected, &mut expected_modifier, 0.016);
+        }
+
+        for index in 0..expected.len() {
+            assert_eq!(
+                expected.get(index),
+                model.base_matrix.get(index),
+                "at index {}",
+                index
+            );
+        }
+    }
+
+    #[test]
+    fn test_von_neumann_skips_diagonal_neighbours() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(5, 5)));
+        model.neighbourhood = NeighbourhoodKind::VonNeumann;
+        model.config.decay_factor = 0.0;
+        model.base_matrix[(2, 2)] = 50.0;
+        model.update(0.016);
+
+        assert_eq!(10.0, model.base_matrix[(2, 2)]);
+        for &(x, y) in &[(2, 1), (1, 2), (3, 2), (2, 3)] {
+            assert_eq!(10.0, model.base_matrix[(x, y)]);
+        }
+        for &(x, y) in &[(1, 1), (3, 1), (1, 3), (3, 3)] {
+            assert_eq!(0.0, model.base_matrix[(x, y)]);
+        }
+    }
+
+    #[test]
+    fn test_von_neumann_conserves_mass_at_the_edge() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(4, 5)));
+        model.neighbourhood = NeighbourhoodKind::VonNeumann;
+        model.conserve_mass = true;
+        model.config.decay_factor = 0.0;
+        model.base_matrix[(0, 0)] = 50.0;
+        model.update(0.016);
+
+        assert_eq!(30.0, model.base_matrix[(0, 0)]);
+        assert_eq!(0.0, model.base_matrix[(1, 1)]);
+        assert_eq!(50.0, model.base_matrix.iter().sum::<f32>());
+    }
+
+    #[test]
+    fn test_same_rng_seed_gives_same_random_pattern() {
+        let config = SimulationConfig {
+            rng_seed: Some(42),
+            ..SimulationConfig::with_resolution(Rect::new(16, 24))
+        };
+        let first = Model::new_seeded(&config, Pattern::Random);
+        let second = Model::new_seeded(&config, Pattern::Random);
+
+        assert_eq!(first.base_matrix.as_slice(), second.base_matrix.as_slice());
+        assert_ne!(0, first.base_matrix.count_nonzero());
+
+        let other = Model::new_seeded(
+            &SimulationConfig {
+                rng_seed: Some(43),
+                ..config
+            },
+            Pattern::Random,
+        );
+        assert_ne!(first.base_matrix.as_slice(), other.base_matrix.as_slice());
+    }
+
+    #[test]
+    fn test_noise_perturbs_a_fraction_of_cells() {
+        let mut model = Model::new(&SimulationConfig {
+            rng_seed: Some(7),
+            ..SimulationConfig::with_resolution(Rect::new(20, 50))
+        });
+        model.noise_rate = 0.02;
+        model.inject_noise();
+
+        assert_eq!(20, model.base_matrix.count_nonzero());
+        assert!(model
+            .base_matrix
+            .iter()
+            .all(|value| *value == 0.0 || *value == DEFAULT_NOISE_AMOUNT));
+    }
+
+    #[test]
+    fn test_noise_keeps_a_settled_canvas_alive() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(20, 50)));
+        for _ in 0..SETTLE_TICKS {
+            model.update(0.016);
+        }
+        assert!(model.is_settled());
+
+        model.noise_rate = 0.02;
+        model.update(0.016);
+        assert!(!model.is_settled());
+        assert_ne!(0, model.base_matrix.count_nonzero());
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(4, 5)));
+        model.stamp_brush(1, 2, DEFAULT_MAX_VALUE);
+        model.rgb_matrix.channel_mut(0)[(3, 3)] = 1.0;
+        model.previous_mouse_xy = Some(Vector2::new(1.0, 2.0));
+        model.clear();
+
+        assert_eq!(0, model.base_matrix.count_nonzero());
+        assert_eq!(0, model.back_matrix.count_nonzero());
+        assert_eq!(0, model.rgb_matrix.channel(0).count_nonzero());
+        assert!(model.previous_mouse_xy.is_none());
+    }
+
+    #[test]
+    fn test_invert() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(4, 5)));
+        model.base_matrix[(1, 2)] = 100.0;
+        model.invert();
+
+        assert_eq!(DEFAULT_MAX_VALUE - 100.0, model.base_matrix[(1, 2)]);
+        assert_eq!(DEFAULT_MAX_VALUE, model.base_matrix[(0, 0)]);
+    }
+
+    #[test]
+    fn test_gravity_kernel_moves_ink_south() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(9, 9)));
+        model.kernel = Kernel::gravity(2.0);
+        model.config.decay_factor = 0.0;
+        model.base_matrix[(4, 4)] = 120.0;
+        model.update(0.016);
+
+        let row = |y: usize| (3..=5).map(|x| model.base_matrix[(x, y)]).sum::<f32>();
+        assert!(row(5) > row(3));
+        assert_eq!(8.0, model.base_matrix[(4, 4)]);
+        assert_eq!(24.0, model.base_matrix[(4, 5)]);
+        assert!((120.0 - model.base_matrix.sum()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_retention_splits_center_cell() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(3, 3)));
+        model.config.decay_factor = 0.0;
+        model.retention = Some(0.5);
+        model.base_matrix[(1, 1)] = 80.0;
+        model.update(0.016);
+
+        assert_eq!(40.0, model.base_matrix[(1, 1)]);
+        for direction in Direction::ALL.iter() {
+            let (dx, dy) = direction.offset();
+            let (x, y) = ((1 + dx) as usize, (1 + dy) as usize);
+            assert_eq!(5.0, model.base_matrix[(x, y)]);
+        }
+    }
+
+    #[test]
+    fn test_default_retention_is_a_ninth() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(3, 3)));
+        model.config.decay_factor = 0.0;
+        model.base_matrix[(1, 1)] = 90.0;
+        model.update(0.016);
+
+        assert_eq!(10.0, model.base_matrix[(1, 1)]);
+        assert_eq!(10.0, model.base_matrix[(0, 0)]);
+    }
+
+    #[test]
+    fn test_max_transfer_slows_spreading() {
+        let spread_after = |max_transfer: Option<f32>, ticks: usize| {
+            let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(11, 11)));
+            model.config.decay_factor = 0.0;
+            model.max_transfer = max_transfer;
+            model.base_matrix[(5, 5)] = DEFAULT_MAX_VALUE;
+            for _ in 0..ticks {
+                model.update(0.016);
+            }
+
+            model
+        };
+
+        let capped = spread_after(Some(5.0), 1);
+        // Each neighbour gets 5 rather than a ninth of the cell, and the cell keeps the rest
+        assert_eq!(5.0, capped.base_matrix[(6, 5)]);
+        assert_eq!(DEFAULT_MAX_VALUE - 40.0, capped.base_matrix[(5, 5)]);
+        assert!((DEFAULT_MAX_VALUE - capped.base_matrix.sum()).abs() < 1e-3);
+
+        let (capped, uncapped) = (spread_after(Some(5.0), 3), spread_after(None, 3));
+        assert!(capped.base_matrix[(5, 5)] > uncapped.base_matrix[(5, 5)]);
+        assert!(capped.base_matrix[(8, 5)] < uncapped.base_matrix[(8, 5)]);
+    }
+
+    #[test]
+    fn test_stroke_dragged_off_the_right_edge_paints_the_border_column() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(5, 10)));
+        model.paused = true;
+        model.left_click_is_held_down = true;
+        model.mouse_xy = Vector2::new(5.0, 2.0);
+        model.update(0.016);
+
+        model.mouse_xy = Vector2::new(14.0, 3.0);
+        model.update(0.016);
+
+        assert_eq!(DEFAULT_MAX_VALUE, model.base_matrix[(9, 3)]);
+        assert_eq!(DEFAULT_MAX_VALUE, model.base_matrix[(8, 2)]);
+        let previous = model.previous_mouse_xy.unwrap();
+        assert_eq!((9.0, 3.0), (previous.x, previous.y));
+
+        // Dragging along outside the canvas keeps painting the nearest edge cells
+        model.mouse_xy = Vector2::new(14.0, 0.0);
+        model.update(0.016);
+        assert_eq!(DEFAULT_MAX_VALUE, model.base_matrix[(9, 1)]);
+        assert_eq!(DEFAULT_MAX_VALUE, model.base_matrix[(9, 0)]);
+    }
+
+    #[test]
+    fn test_stroke_stops_at_the_edge_without_clamping() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(5, 10)));
+        model.paused = true;
+        model.clamp_strokes = false;
+        model.left_click_is_held_down = true;
+        model.mouse_xy = Vector2::new(5.0, 2.0);
+        model.update(0.016);
+
+        model.mouse_xy = Vector2::new(14.0, 3.0);
+        model.update(0.016);
+
+        assert_eq!(0.0, model.base_matrix[(9, 3)]);
+        assert!(model.previous_mouse_xy.is_none());
+    }
+
+    #[test]
+    fn test_frozen_cell_keeps_its_value() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(9, 9)));
+        model.base_matrix[(2, 2)] = DEFAULT_MAX_VALUE;
+        model.base_matrix[(6, 6)] = DEFAULT_MAX_VALUE;
+        model.frozen[(2, 2)] = true;
+
+        // A frozen cell doesn't spill over, so its neighbours stay empty
+        model.update(0.016);
+        assert_eq!(0.0, model.base_matrix[(1, 1)]);
+        assert!(model.base_matrix[(5, 5)] > 0.0);
+
+        for _ in 0..100 {
+            model.update(0.016);
+        }
+
+        assert_eq!(DEFAULT_MAX_VALUE, model.base_matrix[(2, 2)]);
+        assert!(model.base_matrix[(6, 6)] < DEFAULT_MAX_VALUE / 10.0);
+    }
+
+    #[test]
+    fn test_frozen_cell_neither_gains_nor_loses_ink() {
+        for &max_transfer in &[None, Some(5.0)] {
+            let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(9, 9)));
+            model.config.decay_factor = 0.0;
+            model.config.value_cutoff = 0.0;
+            model.conserve_mass = true;
+            model.max_transfer = max_transfer;
+            model.base_matrix[(4, 4)] = 100.0;
+            model.base_matrix[(5, 4)] = 60.0;
+            model.frozen[(5, 4)] = true;
+
+            for _ in 0..20 {
+                model.update(0.016);
+            }
+
+            assert_eq!(60.0, model.base_matrix[(5, 4)]);
+            let total: f32 = model.base_matrix.iter().sum();
+            assert!((total - 160.0).abs() < 1e-3, "total ink was {}", total);
+        }
+    }
+
+    #[test]
+    fn test_gaussian_blur_doesnt_lose_ink_to_a_frozen_cell() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(15, 15)));
+        model.config.decay_factor = 0.0;
+        model.config.value_cutoff = 0.0;
+        model.diffusion_kernel = DiffusionKernel::Gaussian;
+        model.sigma = 0.5;
+        model.base_matrix[(7, 7)] = 100.0;
+        model.base_matrix[(8, 7)] = 60.0;
+        model.frozen[(8, 7)] = true;
+
+        // Not long enough for any ink to reach the edge of the canvas
+        for _ in 0..2 {
+            model.update(0.016);
+        }
+
+        assert_eq!(60.0, model.base_matrix[(8, 7)]);
+        let total: f32 = model.base_matrix.iter().sum();
+        assert!((total - 160.0).abs() < 1e-3, "total ink was {}", total);
+    }
+
+    #[test]
+    fn test_freezing_brush_freezes_and_thaws() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(5, 5)));
+        model.brush_radius = 2;
+        model.freezing = true;
+        model.paint(2, 2, false, 0.016);
+
+        assert!(model.frozen[(2, 2)]);
+        assert!(model.frozen[(1, 2)]);
+        assert!(!model.frozen[(0, 0)]);
+        assert_eq!(0, model.base_matrix.count_nonzero());
+
+        model.paint(2, 2, true, 0.016);
+        assert!(model.frozen.iter().all(|frozen| !*frozen));
+    }
+
+    #[test]
+    fn test_on_tick_fires_once_per_update() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(3, 3)));
+        model.config.decay_factor = 0.0;
+        model.base_matrix[(1, 1)] = 90.0;
+
+        let sums = Arc::new(Mutex::new(Vec::new()));
+        let observed = Arc::clone(&sums);
+        model.on_tick = Some(Box::new(move |model: &Model| {
+            observed.lock().unwrap().push(model.base_matrix.sum());
+        }));
+
+        model.update(0.016);
+        model.paused = true;
+        model.update(0.016);
+
+        let sums = sums.lock().unwrap();
+        assert_eq!(2, sums.len());
+        assert!(sums.iter().all(|sum| (sum - 90.0).abs() < 1e-3));
+        assert_eq!(10.0, model.base_matrix[(0, 0)]);
+        assert!(model.on_tick.is_some());
+    }
+
+    #[test]
+    fn test_aspect_spreads_less_ink_to_the_sides() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(3, 3)));
+        model.config.decay_factor = 0.0;
+        model.aspect = 2.0;
+        model.retention = Some(0.5);
+        model.base_matrix[(1, 1)] = 80.0;
+        model.update(0.016);
+
+        assert_eq!(40.0, model.base_matrix[(1, 1)]);
+        assert_eq!(model.base_matrix[(0, 1)], model.base_matrix[(2, 1)]);
+        assert_eq!(model.base_matrix[(1, 0)], model.base_matrix[(1, 2)]);
+        assert!(
+            (4.0 * model.base_matrix[(0, 1)] - model.base_matrix[(1, 0)]).abs() < 1e-4,
+            "east and west should get a quarter as much as north and south"
+        );
+    }
+
+    #[test]
+    fn test_anti_diffusion_steepens_a_soft_edge() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(1, 8)));
+        model.config.decay_factor = 0.0;
+        model.config.value_cutoff = 1.0;
+        model.diffusion_mode = DiffusionMode::AntiDiffuse;
+        for (index, value) in [0.0, 0.5, 0.0, 50.0, 100.0, 100.0, 100.0, 100.0]
+            .iter()
+            .enumerate()
+        {
+            model.base_matrix[(index, 0)] = *value;
+        }
+
+        let steepest = |matrix: &Matrix2D| {
+            matrix
+                .as_slice()
+                .windows(2)
+                .map(|pair| (pair[1] - pair[0]).abs())
+                .fold(0.0, f32::max)
+        };
+        let before = steepest(&model.base_matrix);
+        model.update(0.016);
+
+        assert!(steepest(&model.base_matrix) > before);
+        // The dark side of the edge is clamped rather than going negative
+        assert_eq!(0.0, model.base_matrix[(2, 0)]);
+        assert_eq!(
+            100.0 + ANTI_DIFFUSION_STRENGTH * 25.0,
+            model.base_matrix[(4, 0)]
+        );
+        // A faint cell is sharpened, but not past the cutoff, so it's emptied
+        assert_eq!(0.0, model.base_matrix[(1, 0)]);
+    }
+
+    #[test]
+    fn test_exponential_decay_fades_bright_cells_faster_than_dim_ones() {
+        let after_a_tick = |decay_mode: DecayMode, value: f32| {
+            let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(1, 1)));
+            model.decay_mode = decay_mode;
+            model.conserve_mass = true;
+            model.base_matrix[(0, 0)] = value;
+            model.update(MAX_FRAME_TIME);
+            model.base_matrix[(0, 0)]
+        };
+
+        let bright = 400.0;
+        assert!(
+            after_a_tick(DecayMode::Exponential, bright) < after_a_tick(DecayMode::Linear, bright)
+        );
+        let dim = 0.01;
+        assert!(after_a_tick(DecayMode::Exponential, dim) > 0.0);
+        assert_eq!(0.0, after_a_tick(DecayMode::Linear, dim));
+    }
+
+    #[test]
+    fn test_exponential_decay_never_goes_negative() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(6, 6)));
+        model.decay_mode = DecayMode::Exponential;
+        model.config.decay_factor = 0.9;
+        model.base_matrix[(3, 3)] = DEFAULT_MAX_VALUE;
+        for _ in 0..20 {
+            model.update(MAX_FRAME_TIME);
+            assert!(model.base_matrix.min().unwrap() >= 0.0);
+        }
+        assert!(model.base_matrix.count_nonzero() > 0);
+    }
+
+    #[test]
+    fn test_exponential_decay_rate_is_capped_below_one() {
+        assert_eq!(1.5, DecayMode::Linear.cap_rate(1.5));
+        assert_eq!(
+            MAX_EXPONENTIAL_DECAY_RATE,
+            DecayMode::Exponential.cap_rate(1.5)
+        );
+        assert_eq!(0.5, DecayMode::Exponential.cap_rate(0.5));
+        match DecayMode::Exponential.per_tick(2.0, MAX_FRAME_TIME) {
+            Decay::Exponential(factor) => assert!(factor > 0.0),
+            decay => panic!("expected exponential decay, got {:?}", decay),
+        }
+    }
+
+    #[test]
+    fn test_conserve_mass_keeps_spillover_at_the_edge() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(4, 5)));
+        model.conserve_mass = true;
+        model.config.decay_factor = 0.0;
+        model.base_matrix[(0, 0)] = 90.0;
+        model.base_matrix[(4, 2)] = 45.0;
+        model.update(0.016);
+
+        let total: f32 = model.base_matrix.iter().sum();
+        assert!((135.0 - total).abs() < 1e-4, "total was {}", total);
+        // The corner cell keeps its own ninth and the five ninths meant for neighbours that don't exist
+        assert!((60.0 - model.base_matrix[(0, 0)]).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_conserve_mass_keeps_gaussian_blur_at_the_edge() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(4, 5)));
+        model.conserve_mass = true;
+        model.config.decay_factor = 0.0;
+        model.config.value_cutoff = 0.0;
+        model.diffusion_kernel = DiffusionKernel::Gaussian;
+        model.base_matrix[(0, 0)] = 90.0;
+        model.base_matrix[(4, 2)] = 45.0;
+        model.update(0.016);
+
+        let total: f32 = model.base_matrix.iter().sum();
+        assert!((135.0 - total).abs() < 1e-3, "total was {}", total);
+        assert!(model.base_matrix[(0, 0)] < 90.0);
+        assert!(model.base_matrix[(1, 1)] > 0.0);
+
+        // Without it, the part of the corner's blur that's off the canvas is lost
+        model.conserve_mass = false;
+        model.update(0.016);
+        let total: f32 = model.base_matrix.iter().sum();
+        assert!(total < 135.0 - 1.0, "total was {}", total);
+    }
+
+    #[test]
+    fn test_edge_spillover_is_lost_without_conserve_mass() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(4, 5)));
+        model.config.decay_factor = 0.0;
+        model.base_matrix[(0, 0)] = 90.0;
+        model.update(0.016);
+
+        assert!((40.0 - model.base_matrix.iter().sum::<f32>()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_custom_cutoff_zeroes_faint_paint() {
+        for &kernel in &[DiffusionKernel::Uniform, DiffusionKernel::Gaussian] {
+            let mut model = Model::new(&SimulationConfig {
+                decay_factor: 0.0,
+                value_cutoff: 0.5,
+                ..SimulationConfig::with_resolution(Rect::new(5, 5))
+            });
+            model.diffusion_kernel = kernel;
+            model.stamp_brush(2, 2, 0.3);
+            model.update(0.016);
+
+            assert!(
+                model.base_matrix.iter().all(|value| *value == 0.0),
+                "{:?}",
+                kernel
+            );
+        }
+    }
+
+    #[test]
+    fn test_seeded_canvas_is_capped_at_max_value() {
+        let mut model = Model::new(&SimulationConfig {
+            max_value: 10.0,
+            ..SimulationConfig::with_resolution(Rect::new(4, 4))
+        });
+        model.seed_pattern(Pattern::Checkerboard);
+
+        assert_eq!(10.0, model.base_matrix[(0, 0)]);
+    }
+
+    #[test]
+    fn test_custom_config() {
+        let config = SimulationConfig {
+            decay_factor: 2.0,
+            max_value: 10.0,
+            resolution: Rect::new(5, 7),
+            rng_seed: None,
+            value_cutoff: 1.0,
+        };
+        let mut model = Model::new(&config);
+        assert_eq!((7, 5), (model.base_matrix.w(), model.base_matrix.h()));
+
+        model.base_matrix[(3, 2)] = 45.0;
+        model.base_matrix[(0, 0)] = 0.5;
+        model.update(0.1);
+
+        // The decay is 2.0 * 0.1, and the cell under the cutoff is emptied rather than spilling over
+        assert_eq!(4.8, model.base_matrix[(3, 2)]);
+        assert_eq!(0.0, model.base_matrix[(0, 0)]);
+
+        // Cells are clamped to max_value
+        model.base_matrix[(3, 2)] = 900.0;
+        model.update(0.1);
+        assert_eq!(10.0, model.base_matrix[(3, 2)]);
+    }
+
+    #[test]
+    fn test_red_stamp_only_diffuses_red_channel() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(9, 9)));
+        model.color_mode = ColorMode::Rgb;
+        model.paint_color = [1.0, 0.0, 0.0];
+        model.stamp_brush_rgb(4, 4, false);
+
+        for _ in 0..3 {
+            model.update(0.016);
+        }
+
+        assert!(model.rgb_matrix.channel(0)[(4, 4)] > 0.0);
+        assert!(model.rgb_matrix.channel(0)[(3, 3)] > 0.0);
+        assert!(model
+            .rgb_matrix
+            .channel(1)
+            .iter()
+            .all(|value| *value == 0.0));
+        assert!(model
+            .rgb_matrix
+            .channel(2)
+            .iter()
+            .all(|value| *value == 0.0));
+        assert!(model.base_matrix.iter().all(|value| *value == 0.0));
+    }
+
+    #[test]
+    fn test_rgb_erase_only_clears_selected_channels() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(5, 5)));
+        model.color_mode = ColorMode::Rgb;
+        model.paint_color = [1.0, 1.0, 1.0];
+        model.stamp_brush_rgb(2, 2, false);
+        model.paint_color = [0.0, 1.0, 0.0];
+        model.stamp_brush_rgb(2, 2, true);
+
+        assert_eq!(
+            Some([DEFAULT_MAX_VALUE, 0.0, DEFAULT_MAX_VALUE]),
+            model.rgb_matrix.get(12)
+        );
+    }
+
+    #[test]
+    fn test_draw_with_palette() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(1, 2)));
+        model.palette = Palette::Heat;
+        model.base_matrix[(1, 0)] = 1.0;
+
+        let mut frame = vec![0; 8];
+        model.draw(&mut frame).unwrap();
+
+        assert_eq!(vec![0, 0, 0, 0xff, 255, 255, 255, 0xff], frame);
+    }
+
+    #[test]
+    fn test_gamma() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(1, 4)));
+        for (index, value) in model.base_matrix.iter_mut().enumerate() {
+            *value = index as f32 / 3.0;
+        }
+
+        let mut frame = vec![0; 16];
+        model.draw(&mut frame).unwrap();
+        let expected: Vec<u8> = model
+            .base_matrix
+            .iter()
+            .flat_map(|value| Palette::Grayscale.color(*value).to_vec())
+            .collect();
+        assert_eq!(expected, frame);
+
+        // Grayscale ink is drawn dark on light, so a darker midtone is a smaller byte
+        model.gamma = 2.2;
+        let mut corrected = vec![0; 16];
+        model.draw(&mut corrected).unwrap();
+        assert!(corrected[4] < frame[4]);
+        assert!(corrected[8] < frame[8]);
+        assert_eq!(frame[0..4], corrected[0..4]);
+        assert_eq!(frame[12..16], corrected[12..16]);
+    }
+
+    /// Draw a grayscale ramp from empty to full, returning the red byte of each pixel
+    fn draw_ramp(render_mode: RenderMode) -> Vec<u8> {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(1, 11)));
+        model.render_mode = render_mode;
+        for (index, value) in model.base_matrix.iter_mut().enumerate() {
+            *value = index as f32 / 10.0;
+        }
+
+        let mut frame = vec![0; 44];
+        model.draw(&mut frame).unwrap();
+        assert!(model
+            .base_matrix
+            .iter()
+            .enumerate()
+            .all(|(index, value)| *value == index as f32 / 10.0));
+
+        frame.chunks(4).map(|pixel| pixel[0]).collect()
+    }
+
+    #[test]
+    fn test_threshold_render_mode() {
+        assert_eq!(
+            vec![255, 255, 255, 255, 255, 0, 0, 0, 0, 0, 0],
+            draw_ramp(RenderMode::Threshold(0.5))
+        );
+    }
+
+    #[test]
+    fn test_posterize_render_mode() {
+        assert_eq!(
+            vec![255, 255, 255, 127, 127, 127, 127, 127, 0, 0, 0],
+            draw_ramp(RenderMode::Posterize(3))
+        );
+        assert_eq!(
+            draw_ramp(RenderMode::Posterize(2)),
+            draw_ramp(RenderMode::Posterize(0))
+        );
+    }
+
+    #[test]
+    fn test_buffer_to_canvas() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(8, 8)));
+        let cell_under = |model: &Model, x: f32, y: f32| {
+            let Vector2 { x, y } = model.buffer_to_canvas(Vector2::new(x, y));
+            (x.round() as usize, y.round() as usize)
+        };
+
+        assert_eq!((3, 5), cell_under(&model, 3.0, 5.0));
+
+        model.zoom = 4;
+        assert_eq!((0, 0), cell_under(&model, 3.0, 0.0));
+        assert_eq!((1, 1), cell_under(&model, 4.0, 7.0));
+        assert_eq!((1, 2), cell_under(&model, 7.0, 8.0));
+
+        model.zoom = 2;
+        assert_eq!((3, 2), cell_under(&model, 7.0, 4.0));
+    }
+
+    #[test]
+    fn test_stroke_from_the_top_left_pixel_when_zoomed() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(8, 8)));
+        model.paused = true;
+        model.set_zoom(2);
+        model.left_click_is_held_down = true;
+        model.mouse_xy = model.buffer_to_canvas(Vector2::new(0.0, 0.0));
+        model.update(0.016);
+
+        assert!(model.base_matrix[(0, 0)] > 0.0);
+    }
+
+    #[test]
+    fn test_buffer_to_canvas_when_panned() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(8, 8)));
+        model.set_zoom(4);
+        model.pan_by(Vector2::new(2.0, 3.0));
+
+        let Vector2 { x, y } = model.buffer_to_canvas(Vector2::new(4.0, 0.0));
+        assert_eq!((3, 3), (x.round() as usize, y.round() as usize));
+        let Vector2 { x, y } = model.buffer_to_canvas(Vector2::new(7.0, 7.0));
+        assert_eq!((3, 4), (x.round() as usize, y.round() as usize));
+    }
+
+    #[test]
+    fn test_pan_is_clamped_to_canvas() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(8, 8)));
+        model.pan_by(Vector2::new(3.0, 3.0));
+        assert_eq!((0.0, 0.0), (model.pan_offset.x, model.pan_offset.y));
+
+        model.set_zoom(4);
+        model.pan_by(Vector2::new(100.0, -2.0));
+        assert_eq!((6.0, 0.0), (model.pan_offset.x, model.pan_offset.y));
+
+        // The bottom-right pixel still lands on the last cell
+        let Vector2 { x, .. } = model.buffer_to_canvas(Vector2::new(7.0, 0.0));
+        assert_eq!(7, x.round() as usize);
+
+        model.set_zoom(1);
+        assert_eq!((0.0, 0.0), (model.pan_offset.x, model.pan_offset.y));
+    }
+
+    #[test]
+    fn test_draw_zoomed() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(2, 4)));
+        model.palette = Palette::Heat;
+        model.zoom = 2;
+        model.base_matrix[(0, 0)] = 1.0;
+
+        let mut frame = vec![0; 32];
+        model.draw(&mut frame).unwrap();
+        let lit: Vec<bool> = frame.chunks(4).map(|pixel| pixel[0] == 255).collect();
+
+        assert_eq!(
+            vec![true, true, false, false, true, true, false, false],
+            lit
+        );
+    }
+
+    #[test]
+    fn test_draw_with_sim_scale_fills_a_block_per_cell() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(2, 3)));
+        model.palette = Palette::Heat;
+        model.sim_scale = 2;
+        model.base_matrix[(1, 1)] = 1.0;
+        assert_eq!((6, 4), model.frame_size());
+
+        let mut frame = vec![0; 6 * 4 * 4];
+        model.draw(&mut frame).unwrap();
+        let lit: Vec<(usize, usize)> = frame
+            .chunks(4)
+            .enumerate()
+            .filter(|(_, pixel)| pixel[0] == 255)
+            .map(|(index, _)| calculate_xy_from_index(index, 6))
+            .collect();
+
+        assert_eq!(vec![(2, 2), (3, 2), (2, 3), (3, 3)], lit);
+        assert!(model.draw(&mut [0; 6 * 4]).is_err());
+    }
+
+    #[test]
+    fn test_grid_tints_every_fourth_cell() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(6, 9)));
+        model.base_matrix[(5, 2)] = 0.5;
+        model.grid_spacing = 4;
+        let canvas = model.base_matrix.clone();
+
+        let mut plain = vec![0; 9 * 6 * 4];
+        model.draw(&mut plain).unwrap();
+        model.show_grid = true;
+        let mut gridded = vec![0; 9 * 6 * 4];
+        model.draw(&mut gridded).unwrap();
+
+        for (index, (plain, gridded)) in plain.chunks(4).zip(gridded.chunks(4)).enumerate() {
+            let (x, y) = calculate_xy_from_index(index, 9);
+            if x % 4 == 0 || y % 4 == 0 {
+                let [r, g, b] = tint_with_grid([plain[0], plain[1], plain[2]]);
+                assert_eq!(
+                    &[r, g, b, plain[3]][..],
+                    gridded,
+                    "{{{}, {}}} should be tinted",
+                    x,
+                    y
+                );
+            } else {
+                assert_eq!(plain, gridded, "{{{}, {}}} should be untouched", x, y);
+            }
+        }
+        // Empty cells are drawn white, so the grid darkens them
+        assert!(gridded[0] < plain[0]);
+        assert_eq!(canvas, model.base_matrix);
+    }
+
+    #[test]
+    fn test_buffer_to_canvas_with_sim_scale() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(4, 4)));
+        model.sim_scale = 2;
+        let cell_under = |model: &Model, x: f32, y: f32| {
+            let Vector2 { x, y } = model.buffer_to_canvas(Vector2::new(x, y));
+            (x.round() as usize, y.round() as usize)
+        };
+
+        assert_eq!((1, 1), cell_under(&model, 2.0, 3.0));
+        assert_eq!((3, 0), cell_under(&model, 7.0, 1.0));
+
+        model.zoom = 2;
+        assert_eq!(4, model.pixels_per_cell());
+        assert_eq!((1, 0), cell_under(&model, 7.0, 3.0));
+    }
+
+    #[test]
+    fn test_draw_then_import_round_trips() {
+        let (height, width) = (3, 4);
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(height, width)));
+        for (index, value) in model.base_matrix.iter_mut().enumerate() {
+            *value = index as f32 / 11.0;
+        }
+
+        let mut frame = vec![0; height * width * 4];
+        model.draw(&mut frame).unwrap();
+        let imported = Matrix2D::from_rgba(&frame, width, height).unwrap();
+
+        for (imported, original) in imported.iter().zip(model.base_matrix.iter()) {
+            assert!((imported - original).abs() <= 0.5 / 255.0);
+        }
+    }
+
+    #[test]
+    fn test_bright_background_shows_through_dim_ink() {
+        let (height, width) = (3, 4);
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(height, width)));
+        model.config.decay_factor = 0.0;
+        let mut background = Matrix2D::new(height, width);
+        background[(1, 1)] = 0.8;
+        model.background = Some(background.clone());
+        model.base_matrix[(1, 1)] = 0.2;
+        model.base_matrix[(3, 2)] = 0.6;
+
+        let mut frame = vec![0; height * width * 4];
+        model.draw(&mut frame).unwrap();
+        let drawn = Matrix2D::from_rgba(&frame, width, height).unwrap();
+        assert!((drawn[(1, 1)] - 0.8).abs() <= 0.5 / 255.0);
+        assert!((drawn[(3, 2)] - 0.6).abs() <= 0.5 / 255.0);
+
+        // The background is only drawn, it never diffuses into the canvas or away
+        model.update(0.016);
+        assert_eq!(Some(background), model.background);
+        assert!(model.base_matrix.max().unwrap() < 0.2);
+    }
+
+    #[test]
+    fn test_draw_size_mismatch() {
+        let model = Model::new(&SimulationConfig::with_resolution(Rect::new(2, 4)));
+        let mut frame = vec![7; 28];
+
+        assert!(matches!(
+            model.draw(&mut frame),
+            Err(app::Error::SizeMismatch {
+                frame_pixels: 7,
+                matrix_len: 8
+            })
+        ));
+        assert!(frame.iter().all(|byte| *byte == 7));
+    }
+
+    #[test]
+    fn test_draw_rejects_a_frame_with_a_partial_pixel() {
+        let model = Model::new(&SimulationConfig::with_resolution(Rect::new(2, 4)));
+        let mut frame = vec![7; 35];
+
+        assert!(matches!(
+            model.draw(&mut frame),
+            Err(app::Error::SizeMismatch { .. })
+        ));
+        assert!(frame.iter().all(|byte| *byte == 7));
+    }
+
+    #[test]
+    fn test_draw_hud_stays_in_the_top_left_corner() {
+        let (height, width) = (100, 300);
+        let model = Model::new(&SimulationConfig::with_resolution(Rect::new(height, width)));
+        let mut frame = vec![0x42; height * width * 4];
+        model.draw_hud(&mut frame).unwrap();
+
+        let (hud_width, hud_height) = hud::size(&model.hud_lines());
+        assert!(hud_width < width && hud_height < height);
+
+        let mut changed = 0;
+        for (index, pixel) in frame.chunks(4).enumerate() {
+            let (x, y) = calculate_xy_from_index(index, width);
+            if pixel != [0x42; 4] {
+                assert!(x < hud_width && y < hud_height);
+                changed += 1;
+            }
+        }
+        assert!(changed > 0);
+    }
+
+    #[test]
+    fn test_invert_display() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(1, 3)));
+        model.base_matrix[(1, 0)] = 0.25;
+        model.base_matrix[(2, 0)] = 1.0;
+
+        let mut frame = vec![0; 12];
+        model.draw(&mut frame).unwrap();
+        assert_eq!(
+            vec![255, 255, 255, 0xff, 191, 191, 191, 0xff, 0, 0, 0, 0xff],
+            frame
+        );
+
+        model.invert_display = true;
+        let mut inverted = vec![0; 12];
+        model.draw(&mut inverted).unwrap();
+        assert_eq!(
+            vec![0, 0, 0, 0xff, 64, 64, 64, 0xff, 255, 255, 255, 0xff],
+            inverted
+        );
+        assert_eq!(0.25, model.base_matrix[(1, 0)]);
+    }
+
+    #[test]
+    fn test_draw_rgb() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(1, 2)));
+        model.color_mode = ColorMode::Rgb;
+        model.paint_color = [1.0, 0.0, 0.5];
+        model.stamp_brush_rgb(1, 0, false);
+
+        let mut frame = vec![0; 8];
+        model.draw(&mut frame).unwrap();
+
+        assert_eq!(vec![0, 0, 0, 0xff, 255, 0, 255, 0xff], frame);
+    }
+
+    #[test]
+    fn test_cycle_paint_color_wraps_around() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(1, 1)));
+        for _ in 0..PAINT_COLORS.len() {
+            model.cycle_paint_color();
+        }
+
+        assert_eq!(PAINT_COLORS[0], model.paint_color);
+    }
+
+    #[test]
+    fn test_save_png() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(4, 4)));
+        model.stamp_brush(1, 2, DEFAULT_MAX_VALUE);
+
+        let path = std::env::temp_dir().join("diffuser_test_save_png.png");
+        model.save_png(&path).unwrap();
+        let image = image::open(&path).unwrap().into_rgba8();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!((4, 4), image.dimensions());
+        assert_eq!(&image::Rgba([0, 0, 0, 0xff]), image.get_pixel(1, 2));
+        assert_eq!(&image::Rgba([255, 255, 255, 0xff]), image.get_pixel(0, 0));
+    }
+
+    #[test]
+    fn test_save_png_of_empty_canvas_is_an_error() {
+        let model = Model::new(&SimulationConfig::with_resolution(Rect::new(0, 0)));
+        let path = std::env::temp_dir().join("diffuser_test_save_empty_png.png");
+
+        assert!(matches!(
+            model.save_png(&path),
+            Err(app::Error::EmptyCanvas)
+        ));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_load_image_crops_to_canvas() {
+        let mut image = image::GrayImage::from_pixel(6, 6, image::Luma([255]));
+        image.put_pixel(1, 1, image::Luma([0]));
+        image.put_pixel(5, 5, image::Luma([0]));
+
+        let path = std::env::temp_dir().join("diffuser_test_load_image.png");
+        image.save(&path).unwrap();
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(3, 4)));
+        model.load_image(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(12, model.base_matrix.len());
+        assert_eq!(DEFAULT_MAX_VALUE, model.base_matrix[(1, 1)]);
+        assert_eq!(DEFAULT_MAX_VALUE, model.base_matrix.iter().sum::<f32>());
+    }
+
+    #[test]
+    fn test_sampled_value_is_painted_with() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(5, 5)));
+        model.paused = true;
+        model.base_matrix[(1, 1)] = 123.0;
+        assert_eq!(Some(123.0), model.sample(1, 1));
+        assert_eq!(None, model.sample(5, 1));
+
+        model.paint_value = model.sample(1, 1).unwrap();
+        model.left_click_is_held_down = true;
+        model.mouse_xy = Vector2::new(3.0, 3.0);
+        model.update(0.016);
+
+        assert_eq!(123.0, model.base_matrix[(3, 3)]);
+    }
+
+    #[test]
+    fn test_paint_subpixel_splits_paint_between_four_cells() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(4, 4)));
+        model.paint_subpixel(Vector2::new(1.5, 1.5), 100.0);
+
+        for (x, y) in [(1, 1), (2, 1), (1, 2), (2, 2)].iter() {
+            assert_eq!(25.0, model.base_matrix[(*x, *y)]);
+        }
+        assert_eq!(100.0, model.base_matrix.iter().sum::<f32>());
+    }
+
+    #[test]
+    fn test_paint_subpixel_on_a_cell_paints_only_that_cell() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(4, 4)));
+        model.paint_subpixel(Vector2::new(3.0, 1.0), 100.0);
+        model.paint_subpixel(Vector2::new(3.5, 2.0), 100.0);
+
+        assert_eq!(100.0, model.base_matrix[(3, 1)]);
+        assert_eq!(50.0, model.base_matrix[(3, 2)]);
+        assert_eq!(150.0, model.base_matrix.iter().sum::<f32>());
+    }
+
+    #[test]
+    fn test_slow_stroke_is_painted_between_cells() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(5, 5)));
+        model.paused = true;
+        model.paint_value = 100.0;
+        model.left_click_is_held_down = true;
+        model.previous_mouse_xy = Some(Vector2::new(2.0, 2.0));
+        model.mouse_xy = Vector2::new(2.5, 2.0);
+        model.update(0.016);
+
+        assert_eq!(50.0, model.base_matrix[(2, 2)]);
+        assert_eq!(50.0, model.base_matrix[(3, 2)]);
+    }
+
+    #[test]
+    fn test_click_between_cells_paints_the_nearest_cell() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(5, 5)));
+        model.paused = true;
+        model.paint_value = 100.0;
+        model.left_click_is_held_down = true;
+        model.mouse_xy = Vector2::new(2.4, 2.0);
+        model.update(0.016);
+
+        assert_eq!(100.0, model.base_matrix[(2, 2)]);
+        assert_eq!(100.0, model.base_matrix.iter().sum::<f32>());
+    }
+
+    #[test]
+    fn test_accumulated_paint_builds_up_to_max_value() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(5, 5)));
+        model.paused = true;
+        model.accumulate_paint = true;
+        model.left_click_is_held_down = true;
+        model.mouse_xy = Vector2::new(2.0, 2.0);
+
+        let mut previous_value = 0.0;
+        for _ in 0..10 {
+            model.update(0.1);
+            let value = model.base_matrix[(2, 2)];
+            assert!(value > previous_value || value == DEFAULT_MAX_VALUE);
+            previous_value = value;
+        }
+
+        assert_eq!(DEFAULT_MAX_VALUE, model.base_matrix[(2, 2)]);
+        assert_eq!(DEFAULT_MAX_VALUE, model.base_matrix.sum());
+    }
+
+    #[test]
+    fn test_throttle_suppresses_rapid_calls() {
+        let mut throttle = Throttle::new(Duration::from_millis(250));
+        let start = Instant::now();
+
+        assert!(throttle.ready(start));
+        assert!(!throttle.ready(start));
+        assert!(!throttle.ready(start + Duration::from_millis(249)));
+        assert!(throttle.ready(start + Duration::from_millis(250)));
+        // The window starts over from the last message that got through
+        assert!(!throttle.ready(start + Duration::from_millis(400)));
+        assert!(throttle.ready(start + Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn test_spread_radius() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(15, 15)));
+        assert_eq!(0.0, model.spread_radius(7, 7, 0.0));
+
+        // A gradient that fades out by a tenth each cell away from the center
+        for (x, y, value) in model.base_matrix.iter_xy_mut() {
+            let (dx, dy) = (x as f32 - 7.0, y as f32 - 7.0);
+            *value = (1.0 - (dx * dx + dy * dy).sqrt() / 10.0).max(0.0);
+        }
+
+        // Cells over 0.65 are less than 3.5 cells out, and the farthest of those is 3 across and 1 down
+        assert!((model.spread_radius(7, 7, 0.65) - 10f32.sqrt()).abs() < 1e-5);
+        assert_eq!(0.0, model.spread_radius(7, 7, 0.95));
+        assert_eq!(0.0, model.spread_radius(7, 7, 1.0));
+    }
+
+    #[test]
+    fn test_inject_only_touches_one_cell() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(5, 5)));
+        model.base_matrix[(2, 2)] = 3.0;
+        model.inject(2, 2, 1.0);
+
+        assert_eq!(4.0, model.base_matrix[(2, 2)]);
+        assert_eq!(4.0, model.base_matrix.sum());
+
+        model.inject(2, 2, DEFAULT_MAX_VALUE);
+        assert_eq!(DEFAULT_MAX_VALUE, model.base_matrix[(2, 2)]);
+
+        model.inject(5, 2, 1.0);
+        assert_eq!(DEFAULT_MAX_VALUE, model.base_matrix.sum());
+
+        model.update(0.016);
+        assert!(model.base_matrix[(1, 1)] > 0.0);
+    }
+
+    #[test]
+    fn test_soft_erase_takes_out_erase_strength() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(5, 5)));
+        model.paused = true;
+        model.erase_hard = false;
+        model.base_matrix[(2, 2)] = DEFAULT_MAX_VALUE;
+        model.base_matrix[(3, 3)] = DEFAULT_ERASE_STRENGTH / 2.0;
+
+        model.right_click_is_held_down = true;
+        model.mouse_xy = Vector2::new(2.0, 2.0);
+        model.update(0.016);
+        model.mouse_xy = Vector2::new(3.0, 3.0);
+        model.update(0.016);
+
+        assert_eq!(
+            DEFAULT_MAX_VALUE - DEFAULT_ERASE_STRENGTH,
+            model.base_matrix[(2, 2)]
+        );
+        assert_eq!(0.0, model.base_matrix[(3, 3)]);
+    }
+
+    #[test]
+    fn test_soft_erase_only_takes_ink_out_once_per_stroke() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(5, 5)));
+        model.paused = true;
+        model.erase_hard = false;
+        model.base_matrix[(2, 2)] = DEFAULT_MAX_VALUE;
+
+        // Holding the eraser still, then dragging back over where it started
+        model.right_click_is_held_down = true;
+        for &(x, y) in &[(2.0, 2.0), (2.0, 2.0), (3.0, 2.0), (2.0, 2.0)] {
+            model.mouse_xy = Vector2::new(x, y);
+            model.update(0.016);
+        }
+        assert_eq!(
+            DEFAULT_MAX_VALUE - DEFAULT_ERASE_STRENGTH,
+            model.base_matrix[(2, 2)]
+        );
+
+        // The next stroke erases it again
+        model.right_click_is_held_down = false;
+        model.update(0.016);
+        model.right_click_is_held_down = true;
+        model.update(0.016);
+        assert_eq!(
+            DEFAULT_MAX_VALUE - 2.0 * DEFAULT_ERASE_STRENGTH,
+            model.base_matrix[(2, 2)]
+        );
+    }
+
+    #[test]
+    fn test_hard_erase_empties_cells() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(5, 5)));
+        model.paused = true;
+        model.base_matrix[(2, 2)] = DEFAULT_MAX_VALUE;
+
+        model.right_click_is_held_down = true;
+        model.mouse_xy = Vector2::new(2.0, 2.0);
+        model.update(0.016);
+
+        assert_eq!(0.0, model.base_matrix[(2, 2)]);
+    }
+
+    #[test]
+    fn test_flood_fill_stays_inside_a_painted_ring() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(9, 9)));
+        for y in 1..8 {
+            for x in 1..8 {
+                if x == 1 || x == 7 || y == 1 || y == 7 {
+                    model.base_matrix[(x, y)] = DEFAULT_MAX_VALUE;
+                }
+            }
+        }
+        model.flood_fill(4, 4, 2.0);
+
+        for y in 0..9 {
+            for x in 0..9 {
+                let expected = match (x, y) {
+                    (2..=6, 2..=6) => 2.0,
+                    (1..=7, 1..=7) => DEFAULT_MAX_VALUE,
+                    _ => 0.0,
+                };
+                assert_eq!(
+                    expected,
+                    model.base_matrix[(x, y)],
+                    "at {{x: {}, y: {}}}",
+                    x,
+                    y
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_flood_fill_whole_canvas() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(30, 40)));
+        model.flood_fill(0, 0, 1.0);
+
+        assert!(model.base_matrix.iter().all(|value| *value == 1.0));
+    }
+
+    #[test]
+    fn test_horizontal_symmetry_paints_mirror_cell() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(4, 6)));
+        model.paused = true;
+        model.symmetry = Symmetry::Horizontal;
+        model.left_click_is_held_down = true;
+        model.mouse_xy = Vector2::new(1.0, 2.0);
+        model.update(0.016);
+
+        assert_eq!(DEFAULT_MAX_VALUE, model.base_matrix[(1, 2)]);
+        assert_eq!(DEFAULT_MAX_VALUE, model.base_matrix[(4, 2)]);
+        assert_eq!(
+            2.0 * DEFAULT_MAX_VALUE,
+            model.base_matrix.iter().sum::<f32>()
+        );
+    }
+
+    #[test]
+    fn test_gaussian_blur_falls_off_symmetrically() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(11, 11)));
+        model.diffusion_kernel = DiffusionKernel::Gaussian;
+        model.sigma = 0.5;
+        model.config.decay_factor = 1.0;
+        model.base_matrix[(5, 5)] = 100.0;
+        model.update(0.01);
+
+        let at = |dx: isize, dy: isize| model.base_matrix[((5 + dx) as usize, (5 + dy) as usize)];
+        let center = at(0, 0);
+        let side = at(1, 0);
+        let corner = at(1, 1);
+        assert!(center > side && side > corner && corner > 0.0);
+        for &(dx, dy) in &[(-1, 0), (0, 1), (0, -1)] {
+            assert!((at(dx, dy) - side).abs() < 1e-4);
+        }
+        for &(dx, dy) in &[(-1, -1), (1, -1), (-1, 1)] {
+            assert!((at(dx, dy) - corner).abs() < 1e-4);
+        }
+
+        // Decay is taken from every cell the blur reached, a radius of 2 cells with this sigma
+        let total: f32 = model.base_matrix.iter().sum();
+        assert!(total <= 100.0);
+        assert!(total >= 100.0 - 0.01 * 25.0);
+    }
+
+    #[test]
+    fn test_gaussian_weights_are_normalized() {
+        for &sigma in &[0.1, 0.5, 1.0, 3.0] {
+            let weights = gaussian_weights(sigma);
+            assert_eq!(1, weights.len() % 2);
+            assert!((weights.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_long_frame_time_is_capped() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(5, 5)));
+        model.base_matrix[(2, 2)] = 90.0;
+        model.update(1000.0);
+
+        let expected = 10.0 - DEFAULT_DECAY_FACTOR * MAX_FRAME_TIME;
+        assert!((expected - model.base_matrix[(2, 2)]).abs() < 1e-5);
+        assert!((expected - model.base_matrix[(1, 1)]).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_blank_canvas_settles() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(5, 5)));
+        for _ in 0..SETTLE_TICKS {
+            assert!(!model.is_settled());
+            model.update(0.016);
+        }
+
+        assert!(model.is_settled());
+    }
+
+    #[test]
+    fn test_painting_unsettles() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(5, 5)));
+        for _ in 0..SETTLE_TICKS {
+            model.update(0.016);
+        }
+        model.left_click_is_held_down = true;
+        model.mouse_xy = Vector2::new(2.0, 2.0);
+        model.update(0.016);
+        model.left_click_is_held_down = false;
+
+        assert!(!model.is_settled());
+        model.update(0.016);
+        assert!(model.base_matrix[(1, 1)] > 0.0);
+    }
+
+    #[test]
+    fn test_settled_canvas_stops_diffusing() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(5, 5)));
+        for _ in 0..SETTLE_TICKS {
+            model.update(0.016);
+        }
+        model.base_matrix[(2, 2)] = 9.0;
+        model.update(0.016);
+
+        assert_eq!(9.0, model.base_matrix[(2, 2)]);
+    }
+
+    #[test]
+    fn test_paused_update_only_paints() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(5, 5)));
+        model.base_matrix[(1, 1)] = 1.0;
+        model.paused = true;
+        model.left_click_is_held_down = true;
+        model.mouse_xy = Vector2::new(3.0, 3.0);
+        model.update(0.016);
+
+        assert_eq!(1.0, model.base_matrix[(1, 1)]);
+        assert_eq!(DEFAULT_MAX_VALUE, model.base_matrix[(3, 3)]);
+        assert_eq!(
+            1.0 + DEFAULT_MAX_VALUE,
+            model.base_matrix.iter().sum::<f32>()
+        );
+    }
+
+    #[test]
+    fn test_step_once_while_paused() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(5, 5)));
+        model.base_matrix[(2, 2)] = 9.0;
+        model.paused = true;
+        model.step_once = true;
+        model.update(0.0);
+
+        assert!(!model.step_once);
+        assert_eq!(1.0, model.base_matrix[(2, 2)]);
+        assert_eq!(1.0, model.base_matrix[(1, 1)]);
+
+        model.update(0.0);
+        assert_eq!(1.0, model.base_matrix[(2, 2)]);
+    }
+
+    fn ticks_until_blank(decay_factor: f32) -> usize {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(9, 9)));
+        model.config.decay_factor = decay_factor;
+        model.base_matrix[(4, 4)] = 1.0;
+
+        let mut ticks = 0;
+        while model.base_matrix.iter().any(|value| *value > 0.0) {
+            model.update(0.1);
+            ticks += 1;
+            assert!(ticks < 10_000, "canvas never went blank");
+        }
+
+        ticks
+    }
+
+    #[test]
+    fn test_decay_map_fades_edges_sooner() {
+        let (height, width) = (5, 5);
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(height, width)));
+        model.conserve_mass = true;
+        model.base_matrix.fill(10.0);
+
+        let mut decay_map = Matrix2D::new(height, width);
+        for (index, multiplier) in decay_map.iter_mut().enumerate() {
+            let (x, y) = calculate_xy_from_index(index, width);
+            let is_edge = x == 0 || y == 0 || x == width - 1 || y == height - 1;
+            *multiplier = if is_edge { 4.0 } else { 1.0 };
+        }
+        model.set_decay_map(decay_map).unwrap();
+
+        model.update(0.1);
+        assert!(model.base_matrix[(0, 0)] < model.base_matrix[(2, 2)]);
+
+        while model.base_matrix[(0, 0)] > 0.0 {
+            model.update(0.1);
+        }
+        assert!(model.base_matrix[(2, 2)] > 0.0);
+    }
+
+    #[test]
+    fn test_decay_map_has_to_match_canvas() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(4, 5)));
+
+        assert!(matches!(
+            model.set_decay_map(Matrix2D::new(5, 4)),
+            Err(app::Error::CanvasSizeMismatch { .. })
+        ));
+        assert!(model.set_decay_map(Matrix2D::new(4, 5)).is_ok());
+    }
+
+    #[test]
+    fn test_decay_map_rejects_negative_and_non_finite_multipliers() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(4, 5)));
+
+        for &multiplier in &[-0.5, f32::NAN, f32::INFINITY] {
+            let mut decay_map = Matrix2D::new(4, 5);
+            decay_map[(3, 2)] = multiplier;
+            assert!(matches!(
+                model.set_decay_map(decay_map),
+                Err(app::Error::InvalidDecayMultiplier { x: 3, y: 2, .. })
+            ));
+        }
+        assert!(model.decay_map.is_none());
+    }
+
+    #[test]
+    fn test_higher_decay_factor_blanks_canvas_sooner() {
+        assert!(ticks_until_blank(0.5) < ticks_until_blank(0.05));
+    }
+
+    #[test]
+    fn test_undo_and_redo_a_stroke() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(5, 5)));
+        model.paused = true;
+        model.begin_stroke();
+        model.left_click_is_held_down = true;
+        model.mouse_xy = Vector2::new(2.0, 2.0);
+        model.update(0.016);
+        model.left_click_is_held_down = false;
+        model.end_stroke();
+
+        assert!(model.undo());
+        assert_eq!(0.0, model.base_matrix[(2, 2)]);
+        assert!(model.redo());
+        assert_eq!(DEFAULT_MAX_VALUE, model.base_matrix[(2, 2)]);
+    }
+
+    #[test]
+    fn test_undo_and_redo_an_rgb_stroke() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(5, 5)));
+        model.paused = true;
+        model.color_mode = ColorMode::Rgb;
+        model.base_matrix[(0, 0)] = 10.0;
+        model.begin_stroke();
+        model.left_click_is_held_down = true;
+        model.mouse_xy = Vector2::new(2.0, 2.0);
+        model.update(0.016);
+        model.left_click_is_held_down = false;
+        model.end_stroke();
+        let painted = model.rgb_matrix.get(12).unwrap();
+        assert_ne!([0.0; 3], painted);
+
+        // Switching back to grayscale doesn't change which canvas the stroke is undone on
+        model.color_mode = ColorMode::Grayscale;
+        assert!(model.undo());
+        assert_eq!(Some([0.0; 3]), model.rgb_matrix.get(12));
+        assert_eq!(10.0, model.base_matrix[(0, 0)]);
+        assert!(model.redo());
+        assert_eq!(Some(painted), model.rgb_matrix.get(12));
+        assert_eq!(10.0, model.base_matrix[(0, 0)]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_save_and_load_state() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(4, 5)));
+        model.stamp_brush(1, 2, DEFAULT_MAX_VALUE);
+        model.update(0.016);
+
+        let path = std::env::temp_dir().join("diffuser_test_state.json");
+        model.save_state(&path).unwrap();
+        let mut loaded = Model::new(&SimulationConfig::with_resolution(Rect::new(4, 5)));
+        loaded.load_state(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(model.base_matrix.iter().eq(loaded.base_matrix.iter()));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_load_state_into_differently_sized_canvas_is_an_error() {
+        let path = std::env::temp_dir().join("diffuser_test_state_mismatch.json");
+        Model::new(&SimulationConfig::with_resolution(Rect::new(4, 5)))
+            .save_state(&path)
+            .unwrap();
+        let result =
+            Model::new(&SimulationConfig::with_resolution(Rect::new(5, 5))).load_state(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(result, Err(app::Error::CanvasSizeMismatch { .. })));
+    }
+
+    #[test]
+    fn test_stroke_from_just_past_the_canvas_edge() {
+        let (height, width) = (4, 5);
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(height, width)));
+        model.paused = true;
+        model.left_click_is_held_down = true;
+        model.previous_mouse_xy = Some(Vector2::new(width as f32, height as f32));
+        model.mouse_xy = Vector2::new((width - 2) as f32, (height - 2) as f32);
+        model.update(0.016);
+
+        // The point at exactly {x: width, y: height} is skipped, the rest of the line is painted
+        assert_eq!(
+            DEFAULT_MAX_VALUE,
+            model.base_matrix[(width - 1, height - 1)]
+        );
+        assert_eq!(
+            DEFAULT_MAX_VALUE,
+            model.base_matrix[(width - 2, height - 2)]
+        );
+        assert_eq!(
+            2.0 * DEFAULT_MAX_VALUE,
+            model.base_matrix.iter().sum::<f32>()
+        );
+    }
+
+    #[test]
+    fn test_stroke_leaving_the_canvas_follows_the_edge() {
+        let (height, width) = (4, 5);
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(height, width)));
+        model.paused = true;
+        model.left_click_is_held_down = true;
+        model.previous_mouse_xy = Some(Vector2::new(1.0, 1.0));
+        model.mouse_xy = Vector2::new(10.0, 1.0);
+        model.update(0.016);
+
+        for x in 1..width {
+            assert_eq!(DEFAULT_MAX_VALUE, model.base_matrix[(x, 1)]);
+        }
+        assert_eq!(
+            (width - 1) as f32 * DEFAULT_MAX_VALUE,
+            model.base_matrix.iter().sum::<f32>()
+        );
+    }
+
+    #[test]
+    fn test_resize_keeps_matrices_in_sync() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(4, 5)));
+        model.base_matrix[(1, 1)] = 1.0;
+        model.resize(Rect::new(6, 8));
+
+        assert_eq!(48, model.base_matrix.len());
+        assert_eq!(48, model.back_matrix.len());
+        assert_eq!(1.0, model.base_matrix[(1, 1)]);
+        assert!(model.window_rect.contains(7, 5));
+    }
+
+    #[test]
+    fn test_stamp_brush_with_radius_2() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(5, 5)));
+        model.brush_radius = 2;
+        model.stamp_brush(2, 2, DEFAULT_MAX_VALUE);
+
+        for y in 0..5 {
+            for x in 0..5 {
+                // A disc reaching one cell out from the center, so the corners of its 3x3 box stay empty
+                let (dx, dy) = (x as isize - 2, y as isize - 2);
+                let expected = if dx * dx + dy * dy <= 1 {
+                    DEFAULT_MAX_VALUE
+                } else {
+                    0.0
+                };
+                assert_eq!(
+                    expected,
+                    model.base_matrix[(x, y)],
+                    "at {{x: {}, y: {}}}",
+                    x,
+                    y
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_soft_brush_feathers_its_edge() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(21, 21)));
+        model.brush_radius = 4;
+        model.brush_hardness = 0.0;
+        model.stamp_brush(10, 10, 100.0);
+
+        assert_eq!(100.0, model.base_matrix[(10, 10)]);
+        assert_eq!(100.0, model.base_matrix[(13, 10)]);
+        assert_eq!(50.0, model.base_matrix[(15, 10)]);
+        assert_eq!(50.0, model.base_matrix[(10, 5)]);
+        assert!(model.base_matrix[(16, 10)] > 0.0 && model.base_matrix[(16, 10)] < 50.0);
+        assert_eq!(0.0, model.base_matrix[(17, 10)]);
+    }
+
+    #[test]
+    fn test_soft_brush_edge_only_adds_ink() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(21, 21)));
+        model.brush_radius = 4;
+        model.brush_hardness = 0.0;
+        model.base_matrix[(14, 10)] = 80.0;
+        model.stamp_brush(10, 10, 100.0);
+
+        assert_eq!(80.0, model.base_matrix[(14, 10)]);
+    }
+
+    #[test]
+    fn test_stamp_brush_with_radius_1_paints_one_cell() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(5, 5)));
+        model.stamp_brush(2, 2, DEFAULT_MAX_VALUE);
+
+        assert_eq!(DEFAULT_MAX_VALUE, model.base_matrix[(2, 2)]);
+        assert_eq!(1, model.base_matrix.iter().filter(|v| **v > 0.0).count());
+    }
+
+    #[test]
+    fn test_stamp_brush_clips_at_canvas_edge() {
+        let mut model = Model::new(&SimulationConfig::with_resolution(Rect::new(5, 5)));
+        model.brush_radius = 3;
+        model.stamp_brush(0, 0, DEFAULT_MAX_VALUE);
+
+        // Only the quadrant of the brush that overlaps the canvas gets painted
+        assert_eq!(DEFAULT_MAX_VALUE, model.base_matrix[(0, 0)]);
+        assert_eq!(DEFAULT_MAX_VALUE, model.base_matrix[(2, 0)]);
+        assert_eq!(DEFAULT_MAX_VALUE, model.base_matrix[(1, 1)]);
+        assert_eq!(0.0, model.base_matrix[(2, 2)]);
+        assert_eq!(0.0, model.base_matrix[(3, 0)]);
+        assert_eq!(6, model.base_matrix.iter().filter(|v| **v > 0.0).count());
     }
 }
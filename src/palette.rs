@@ -0,0 +1,120 @@
+/// Colormaps for turning cell values into pixels
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Palette {
+    /// Dark ink on a white background
+    Grayscale,
+    Inferno,
+    Viridis,
+    /// Black through red and yellow to white
+    Heat,
+}
+
+const INFERNO: [[f32; 3]; 5] = [
+    [0.0, 0.0, 4.0],
+    [87.0, 16.0, 110.0],
+    [188.0, 55.0, 84.0],
+    [249.0, 142.0, 9.0],
+    [252.0, 255.0, 164.0],
+];
+
+const VIRIDIS: [[f32; 3]; 5] = [
+    [68.0, 1.0, 84.0],
+    [59.0, 82.0, 139.0],
+    [33.0, 145.0, 140.0],
+    [94.0, 201.0, 98.0],
+    [253.0, 231.0, 37.0],
+];
+
+const HEAT: [[f32; 3]; 4] = [
+    [0.0, 0.0, 0.0],
+    [255.0, 0.0, 0.0],
+    [255.0, 255.0, 0.0],
+    [255.0, 255.0, 255.0],
+];
+
+impl Palette {
+    /// The palette that comes after this one, wrapping back around to the first
+    pub fn next(self) -> Self {
+        use Palette::*;
+        match self {
+            Grayscale => Inferno,
+            Inferno => Viridis,
+            Viridis => Heat,
+            Heat => Grayscale,
+        }
+    }
+
+    /// Map a cell value to an RGBA pixel. Values are clamped to `[0, 1]` first, so anything at or above 1.0 gets the
+    /// color at the end of the palette.
+    pub fn color(self, value: f32) -> [u8; 4] {
+        let [r, g, b] = match self {
+            Palette::Grayscale => {
+                let value = (value.min(1.0) * 255.0).round();
+                let value = (255.0 - value).clamp(0.0, 255.0) as u8;
+                [value, value, value]
+            }
+            Palette::Inferno => interpolate(&INFERNO, value),
+            Palette::Viridis => interpolate(&VIRIDIS, value),
+            Palette::Heat => interpolate(&HEAT, value),
+        };
+
+        [r, g, b, 0xff]
+    }
+}
+
+/// Linearly interpolate between the evenly spaced colors of a lookup table
+fn interpolate(table: &[[f32; 3]], value: f32) -> [u8; 3] {
+    let position = value.clamp(0.0, 1.0) * (table.len() - 1) as f32;
+    let lower = (position.floor() as usize).min(table.len() - 2);
+    let t = position - lower as f32;
+    let (from, to) = (table[lower], table[lower + 1]);
+
+    let channel = |c: usize| (from[c] + (to[c] - from[c]) * t).round().clamp(0.0, 255.0) as u8;
+
+    [channel(0), channel(1), channel(2)]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// How `Model::draw` converted values to pixels before there were palettes
+    fn original_grayscale(value: f32) -> [u8; 4] {
+        let value = (value.min(1.0) * 255.0).round();
+        let value = (255.0 - value).clamp(0.0, 255.0) as u8;
+
+        [value, value, value, 0xff]
+    }
+
+    #[test]
+    fn test_grayscale_matches_original_output() {
+        for step in -10..=600 {
+            let value = step as f32 / 500.0;
+            assert_eq!(original_grayscale(value), Palette::Grayscale.color(value));
+        }
+    }
+
+    #[test]
+    fn test_heat_ends_are_distinct() {
+        assert_eq!([0, 0, 0, 0xff], Palette::Heat.color(0.0));
+        assert_eq!([255, 255, 255, 0xff], Palette::Heat.color(1.0));
+    }
+
+    #[test]
+    fn test_interpolates_between_stops() {
+        // Halfway between black and red
+        assert_eq!([128, 0, 0, 0xff], Palette::Heat.color(1.0 / 6.0));
+        // Values past the end of the table use the last color
+        assert_eq!(Palette::Viridis.color(1.0), Palette::Viridis.color(250.0));
+    }
+
+    #[test]
+    fn test_next_cycles_through_every_palette() {
+        let mut palette = Palette::Grayscale;
+        for _ in 0..4 {
+            palette = palette.next();
+        }
+
+        assert_eq!(Palette::Grayscale, palette);
+    }
+}
@@ -0,0 +1,229 @@
+use crate::app;
+use crate::constants::{DEFAULT_CHECKERBOARD_SIZE, DEFAULT_MAX_VALUE, DEFAULT_SEED_DENSITY};
+use crate::matrix::Matrix2D;
+use rand::Rng;
+use std::str::FromStr;
+
+/// Procedural patterns a canvas can start from, as picked with `--seed`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pattern {
+    Random,
+    Checkerboard,
+    Gradient,
+    Circle,
+    /// A single full cell in the center of the canvas
+    Drop,
+}
+
+impl Pattern {
+    /// Write this pattern into `matrix` using its default settings
+    pub fn apply(self, matrix: &mut Matrix2D, rng: &mut impl Rng) {
+        match self {
+            Pattern::Random => random(matrix, rng, DEFAULT_SEED_DENSITY),
+            Pattern::Checkerboard => checkerboard(matrix, DEFAULT_CHECKERBOARD_SIZE),
+            Pattern::Gradient => gradient(matrix),
+            Pattern::Circle => {
+                let (cx, cy) = (matrix.w() / 2, matrix.h() / 2);
+                let radius = matrix.w().min(matrix.h()) / 4;
+                circle(matrix, cx, cy, radius, DEFAULT_MAX_VALUE);
+            }
+            Pattern::Drop => {
+                let (cx, cy) = (matrix.w() / 2, matrix.h() / 2);
+                drop_at(matrix, cx, cy, DEFAULT_MAX_VALUE);
+            }
+        }
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = app::Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "random" => Ok(Pattern::Random),
+            "checkerboard" => Ok(Pattern::Checkerboard),
+            "gradient" => Ok(Pattern::Gradient),
+            "circle" => Ok(Pattern::Circle),
+            "drop" => Ok(Pattern::Drop),
+            _ => Err(app::Error::UnknownPattern {
+                name: name.to_owned(),
+            }),
+        }
+    }
+}
+
+/// Canned runs picked with `--demo`, which seed the canvas and then leave it to diffuse without needing any input
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Demo {
+    /// A single drop of ink in the center that spreads out into a splash and fades away
+    Drop,
+}
+
+impl Demo {
+    /// The pattern the demo starts from
+    pub fn pattern(self) -> Pattern {
+        match self {
+            Demo::Drop => Pattern::Drop,
+        }
+    }
+}
+
+impl FromStr for Demo {
+    type Err = app::Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "drop" => Ok(Demo::Drop),
+            _ => Err(app::Error::UnknownDemo {
+                name: name.to_owned(),
+            }),
+        }
+    }
+}
+
+/// Fill each cell with ink with a chance of `density`, and empty the rest
+pub fn random(matrix: &mut Matrix2D, rng: &mut impl Rng, density: f32) {
+    let density = density.clamp(0.0, 1.0) as f64;
+    for cell in matrix.iter_mut() {
+        *cell = if rng.gen_bool(density) {
+            DEFAULT_MAX_VALUE
+        } else {
+            0.0
+        };
+    }
+}
+
+/// Alternate between full and empty squares of `cell_size` cells on a side, starting with a full square in the top-left
+/// corner
+pub fn checkerboard(matrix: &mut Matrix2D, cell_size: usize) {
+    let cell_size = cell_size.max(1);
+    for (x, y, cell) in matrix.iter_xy_mut() {
+        *cell = if (x / cell_size + y / cell_size).is_multiple_of(2) {
+            DEFAULT_MAX_VALUE
+        } else {
+            0.0
+        };
+    }
+}
+
+/// Fade from empty on the left edge to full on the right edge
+pub fn gradient(matrix: &mut Matrix2D) {
+    let width = matrix.w();
+    for (x, _, cell) in matrix.iter_xy_mut() {
+        *cell = if width > 1 {
+            x as f32 / (width - 1) as f32 * DEFAULT_MAX_VALUE
+        } else {
+            0.0
+        };
+    }
+}
+
+/// Set every cell within `radius` of `{cx, cy}` to `value`, leaving the rest of the matrix alone
+pub fn circle(matrix: &mut Matrix2D, cx: usize, cy: usize, radius: usize, value: f32) {
+    let (cx, cy, radius) = (cx as isize, cy as isize, radius as isize);
+    for (x, y, cell) in matrix.iter_xy_mut() {
+        let (dx, dy) = (x as isize - cx, y as isize - cy);
+        if dx * dx + dy * dy <= radius * radius {
+            *cell = value;
+        }
+    }
+}
+
+/// Set the cell at `{x, y}` to `value`, leaving the rest of the matrix alone. Does nothing if it's outside the matrix.
+pub fn drop_at(matrix: &mut Matrix2D, x: usize, y: usize, value: f32) {
+    let _ = matrix.set(x, y, value);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_random_density() {
+        let mut matrix = Matrix2D::new(100, 100);
+        random(&mut matrix, &mut StdRng::seed_from_u64(7), 0.25);
+
+        let filled = matrix.count_nonzero();
+        assert!(filled > 2000 && filled < 3000);
+        assert!(matrix
+            .iter()
+            .all(|value| *value == 0.0 || *value == DEFAULT_MAX_VALUE));
+    }
+
+    #[test]
+    fn test_random_extremes() {
+        let mut matrix = Matrix2D::new(4, 5);
+        random(&mut matrix, &mut StdRng::seed_from_u64(7), 0.0);
+        assert_eq!(0, matrix.count_nonzero());
+
+        random(&mut matrix, &mut StdRng::seed_from_u64(7), 1.0);
+        assert_eq!(20, matrix.count_nonzero());
+    }
+
+    #[test]
+    fn test_checkerboard_alternates() {
+        let mut matrix = Matrix2D::new(6, 8);
+        checkerboard(&mut matrix, 2);
+
+        assert_eq!(DEFAULT_MAX_VALUE, matrix[(0, 0)]);
+        assert_eq!(DEFAULT_MAX_VALUE, matrix[(1, 1)]);
+        assert_eq!(0.0, matrix[(2, 0)]);
+        assert_eq!(0.0, matrix[(0, 2)]);
+        assert_eq!(DEFAULT_MAX_VALUE, matrix[(2, 2)]);
+        assert_eq!(24, matrix.count_nonzero());
+    }
+
+    #[test]
+    fn test_gradient_runs_left_to_right() {
+        let mut matrix = Matrix2D::new(3, 5);
+        gradient(&mut matrix);
+
+        for y in 0..3 {
+            assert_eq!(0.0, matrix[(0, y)]);
+            assert_eq!(DEFAULT_MAX_VALUE / 2.0, matrix[(2, y)]);
+            assert_eq!(DEFAULT_MAX_VALUE, matrix[(4, y)]);
+        }
+    }
+
+    #[test]
+    fn test_circle_membership() {
+        let mut matrix = Matrix2D::new(9, 9);
+        circle(&mut matrix, 4, 4, 2, 1.0);
+
+        assert_eq!(1.0, matrix[(4, 4)]);
+        assert_eq!(1.0, matrix[(6, 4)]);
+        assert_eq!(1.0, matrix[(5, 5)]);
+        assert_eq!(0.0, matrix[(6, 6)]);
+        assert_eq!(0.0, matrix[(4, 7)]);
+        assert_eq!(13, matrix.count_nonzero());
+    }
+
+    #[test]
+    fn test_drop_fills_the_center_cell() {
+        let mut matrix = Matrix2D::new(5, 7);
+        Pattern::Drop.apply(&mut matrix, &mut StdRng::seed_from_u64(7));
+
+        assert_eq!(DEFAULT_MAX_VALUE, matrix[(3, 2)]);
+        assert_eq!(1, matrix.count_nonzero());
+    }
+
+    #[test]
+    fn test_demo_from_str() {
+        assert_eq!(Pattern::Drop, "drop".parse::<Demo>().unwrap().pattern());
+        assert!(matches!(
+            "rain".parse::<Demo>(),
+            Err(app::Error::UnknownDemo { .. })
+        ));
+    }
+
+    #[test]
+    fn test_pattern_from_str() {
+        assert_eq!(Pattern::Checkerboard, "checkerboard".parse().unwrap());
+        assert!(matches!(
+            "stripes".parse::<Pattern>(),
+            Err(app::Error::UnknownPattern { .. })
+        ));
+    }
+}
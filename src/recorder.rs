@@ -0,0 +1,170 @@
+use crate::app;
+use crate::constants::{DEFAULT_MAX_RECORDED_FRAMES, RECORDER_FPS};
+use gif::{Encoder, Frame, Repeat};
+use log::{debug, warn};
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// How hard the GIF encoder works to find a good palette for each frame, from 1 (best) to 30 (fastest)
+const GIF_QUANTIZATION_SPEED: i32 = 10;
+
+/// Buffers RGBA frames in memory and writes them out as an animated GIF once recording is finished
+pub struct Recorder {
+    frames: VecDeque<Vec<u8>>,
+    height: u16,
+    max_frames: usize,
+    width: u16,
+}
+
+impl Recorder {
+    /// Start a recording of frames with the given dimensions. GIFs can't be more than `u16::MAX` pixels on a side.
+    pub fn start(width: u32, height: u32) -> Result<Self, app::Error> {
+        let too_large = || app::Error::RecordingTooLarge { width, height };
+        let recorder = Self {
+            frames: VecDeque::new(),
+            height: u16::try_from(height).map_err(|_| too_large())?,
+            max_frames: DEFAULT_MAX_RECORDED_FRAMES,
+            width: u16::try_from(width).map_err(|_| too_large())?,
+        };
+
+        debug!(
+            "Started recording with dimensions (w: {}, h: {})",
+            width, height
+        );
+
+        Ok(recorder)
+    }
+
+    /// Cap the number of frames held in memory. Once the cap is reached the oldest frames are dropped, so the
+    /// recording keeps the last `max_frames` frames.
+    pub fn with_max_frames(mut self, max_frames: usize) -> Self {
+        self.max_frames = max_frames.max(1);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Buffer a copy of an RGBA `frame`. Frames that don't match the dimensions of the recording are skipped.
+    pub fn capture(&mut self, frame: &[u8]) {
+        let expected_len = self.width as usize * self.height as usize * 4;
+        if frame.len() != expected_len {
+            warn!(
+                "Skipped recording a frame of {} bytes, expected {} bytes",
+                frame.len(),
+                expected_len
+            );
+            return;
+        }
+
+        if self.frames.len() >= self.max_frames {
+            let _ = self.frames.pop_front();
+        }
+
+        self.frames.push_back(frame.to_vec());
+    }
+
+    /// Encode every buffered frame into a looping GIF at `RECORDER_FPS`
+    pub fn finish(self, path: &Path) -> Result<(), app::Error> {
+        if self.frames.is_empty() {
+            return Err(app::Error::EmptyRecording);
+        }
+
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = Encoder::new(file, self.width, self.height, &[])?;
+        encoder.set_repeat(Repeat::Infinite)?;
+
+        // GIF frame delays are in hundredths of a second
+        let delay = (100.0 / RECORDER_FPS).round() as u16;
+        for mut rgba in self.frames {
+            let mut frame =
+                Frame::from_rgba_speed(self.width, self.height, &mut rgba, GIF_QUANTIZATION_SPEED);
+            frame.delay = delay;
+            encoder.write_frame(&frame)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn solid_frame(width: usize, height: usize, rgba: [u8; 4]) -> Vec<u8> {
+        rgba.iter()
+            .copied()
+            .cycle()
+            .take(width * height * 4)
+            .collect()
+    }
+
+    #[test]
+    fn test_three_frames_make_a_three_frame_gif() {
+        let mut recorder = Recorder::start(4, 3).unwrap();
+        recorder.capture(&solid_frame(4, 3, [255, 0, 0, 255]));
+        recorder.capture(&solid_frame(4, 3, [0, 255, 0, 255]));
+        recorder.capture(&solid_frame(4, 3, [0, 0, 255, 255]));
+
+        let path = std::env::temp_dir().join("diffuser_test_three_frames.gif");
+        recorder.finish(&path).unwrap();
+
+        let mut decoder = gif::DecodeOptions::new()
+            .read_info(File::open(&path).unwrap())
+            .unwrap();
+        let mut frame_count = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!((4, 3), (frame.width, frame.height));
+            frame_count += 1;
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(3, frame_count);
+    }
+
+    #[test]
+    fn test_capture_drops_oldest_frames_past_the_cap() {
+        let mut recorder = Recorder::start(1, 1).unwrap().with_max_frames(2);
+        recorder.capture(&[1, 1, 1, 255]);
+        recorder.capture(&[2, 2, 2, 255]);
+        recorder.capture(&[3, 3, 3, 255]);
+
+        assert_eq!(2, recorder.len());
+        assert_eq!(vec![2, 2, 2, 255], recorder.frames[0]);
+    }
+
+    #[test]
+    fn test_capture_skips_mismatched_frames() {
+        let mut recorder = Recorder::start(2, 2).unwrap();
+        recorder.capture(&[0; 4]);
+
+        assert!(recorder.is_empty());
+    }
+
+    #[test]
+    fn test_finishing_an_empty_recording_is_an_error() {
+        let recorder = Recorder::start(2, 2).unwrap();
+        let path = std::env::temp_dir().join("diffuser_test_empty_recording.gif");
+
+        assert!(matches!(
+            recorder.finish(&path),
+            Err(app::Error::EmptyRecording)
+        ));
+    }
+
+    #[test]
+    fn test_start_rejects_oversized_recordings() {
+        assert!(matches!(
+            Recorder::start(70_000, 10),
+            Err(app::Error::RecordingTooLarge { .. })
+        ));
+    }
+}
@@ -1,3 +1,7 @@
+use crate::vector2::Vector2;
+use std::ops::Mul;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Rect<NumericType> {
     width: NumericType,
     height: NumericType,
@@ -20,8 +24,100 @@ where
     }
 }
 
+impl<NumericType> Rect<NumericType>
+where
+    NumericType: Copy + PartialOrd + Default,
+{
+    /// Whether `{x, y}` lies within `[0, w) x [0, h)`
+    pub fn contains(&self, x: NumericType, y: NumericType) -> bool {
+        let zero = NumericType::default();
+        (zero..self.height).contains(&y) && (zero..self.width).contains(&x)
+    }
+}
+
+impl<NumericType> Rect<NumericType>
+where
+    NumericType: Copy + Mul<Output = NumericType>,
+{
+    pub fn area(&self) -> NumericType {
+        self.width * self.height
+    }
+}
+
 impl Rect<usize> {
-    pub fn contains(&self, x: usize, y: usize) -> bool {
-        (0..self.height).contains(&(y)) && (0..self.width).contains(&(x))
+    /// Whether the cell nearest to `point` is inside of the rect
+    pub fn contains_point(&self, point: Vector2<f32>) -> bool {
+        let (x, y) = (point.x.round(), point.y.round());
+        x >= 0.0 && y >= 0.0 && self.contains(x as usize, y as usize)
+    }
+
+    /// Move `point` to the nearest position within `[0, w - 1] x [0, h - 1]`
+    pub fn clamp_point(&self, point: Vector2<f32>) -> Vector2<f32> {
+        Vector2::new(
+            point.x.clamp(0.0, self.width.saturating_sub(1) as f32),
+            point.y.clamp(0.0, self.height.saturating_sub(1) as f32),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_contains_usize() {
+        let rect: Rect<usize> = Rect::new(3, 4);
+        assert!(rect.contains(0, 0));
+        assert!(rect.contains(3, 2));
+        assert!(!rect.contains(4, 2));
+        assert!(!rect.contains(3, 3));
+    }
+
+    #[test]
+    fn test_contains_i32() {
+        let rect: Rect<i32> = Rect::new(3, 4);
+        assert!(rect.contains(0, 0));
+        assert!(rect.contains(3, 2));
+        assert!(!rect.contains(-1, 0));
+        assert!(!rect.contains(0, -1));
+        assert!(!rect.contains(4, 2));
+    }
+
+    #[test]
+    fn test_area() {
+        assert_eq!(12usize, Rect::new(3usize, 4).area());
+        assert_eq!(12i32, Rect::new(3i32, 4).area());
+        assert_eq!(0usize, Rect::new(0usize, 4).area());
+    }
+
+    #[test]
+    fn test_contains_point() {
+        let rect = Rect::new(3, 4);
+        assert!(rect.contains_point(Vector2::new(1.0, 1.0)));
+        assert!(rect.contains_point(Vector2::new(0.0, 0.0)));
+        assert!(rect.contains_point(Vector2::new(3.0, 2.0)));
+        assert!(rect.contains_point(Vector2::new(-0.25, 1.0)));
+        assert!(rect.contains_point(Vector2::new(1.0, -0.25)));
+        assert!(rect.contains_point(Vector2::new(3.25, 2.25)));
+
+        assert!(!rect.contains_point(Vector2::new(4.0, 1.0)));
+        assert!(!rect.contains_point(Vector2::new(1.0, 3.0)));
+        assert!(!rect.contains_point(Vector2::new(-0.5, 1.0)));
+        assert!(!rect.contains_point(Vector2::new(1.0, -0.5)));
+        assert!(!rect.contains_point(Vector2::new(3.5, 1.0)));
+    }
+
+    #[test]
+    fn test_clamp_point() {
+        let rect = Rect::new(3, 4);
+        let inside = rect.clamp_point(Vector2::new(1.5, 1.0));
+        let past_bottom_right = rect.clamp_point(Vector2::new(10.0, 3.0));
+        let past_top_left = rect.clamp_point(Vector2::new(-2.0, -0.5));
+        let on_edge = rect.clamp_point(Vector2::new(3.0, 2.0));
+
+        assert_eq!((1.5, 1.0), (inside.x, inside.y));
+        assert_eq!((3.0, 2.0), (past_bottom_right.x, past_bottom_right.y));
+        assert_eq!((0.0, 0.0), (past_top_left.x, past_top_left.y));
+        assert_eq!((3.0, 2.0), (on_edge.x, on_edge.y));
     }
 }
@@ -0,0 +1,708 @@
+//! Recording the input a model gets each tick, and playing it back, so a session can be reproduced exactly.
+//!
+//! Recordings are plain text with one event per line, prefixed with the tick it happened on:
+//!
+//! ```text
+//! 0 m 12.5 40
+//! 0 d l
+//! 7 m 30 41.25
+//! 9 u l
+//! 12 f 30 41 250
+//! 15 kernel gaussian
+//! 20 clear
+//! 21 undo
+//! ```
+//!
+//! A recording starts with how many times a second the model was updated, like `updates-per-second 60`, since each
+//! tick decays and spreads ink by how long it lasts. Recordings without it were made at 60.
+//!
+//! `m x y` moves the mouse to `{x, y}` on the canvas, and `d` and `u` press and release the `l`eft or `r`ight button.
+//! `f x y value` flood fills from the cell at `{x, y}` with `value`, and `inject x y value` injects `value` into it.
+//! `clear`, `invert`, `undo`, `redo`, and `step` do what their keys do. `r`, `k`, `v`, and `i` set the brush radius,
+//! the decay factor, the paint value, and whether the brush is freezing, and every other setting a key changes is set
+//! by name, like `pause 1` or `symmetry radial 6`. Flags are `1` for on and `0` for off. Keys that only change how the
+//! canvas is drawn aren't recorded.
+
+use crate::app;
+use crate::constants::DEFAULT_UPDATES_PER_SECOND;
+use crate::model::{
+    ColorMode, DecayMode, DiffusionKernel, DiffusionMode, Model, NeighbourhoodKind,
+};
+use crate::symmetry::Symmetry;
+use crate::vector2::Vector2;
+use std::fmt;
+use std::path::Path;
+
+/// A mouse button the model tracks, the left one paints and the right one erases
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MouseButton {
+    Left,
+    Right,
+}
+
+/// Something the user did to the model on a tick
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputEvent {
+    /// The mouse moved to `{x, y}` on the canvas
+    Move {
+        x: f32,
+        y: f32,
+    },
+    Press(MouseButton),
+    Release(MouseButton),
+    /// The canvas was flood filled with `value` from the cell at `{x, y}`
+    Fill {
+        x: usize,
+        y: usize,
+        value: f32,
+    },
+    /// `value` was injected into the cell at `{x, y}`
+    Inject {
+        x: usize,
+        y: usize,
+        value: f32,
+    },
+    Clear,
+    Invert,
+    Undo,
+    Redo,
+    /// The model was told to update once while it's paused
+    Step,
+    BrushRadius(usize),
+    DecayFactor(f32),
+    PaintValue(f32),
+    Freezing(bool),
+    Paused(bool),
+    ColorMode(ColorMode),
+    PaintColor([f32; 3]),
+    DiffusionKernel(DiffusionKernel),
+    DiffusionMode(DiffusionMode),
+    DecayMode(DecayMode),
+    Neighbourhood(NeighbourhoodKind),
+    Symmetry(Symmetry),
+    NoiseRate(f32),
+    AccumulatePaint(bool),
+    EraseHard(bool),
+}
+
+impl InputEvent {
+    /// Feed the event to `model` the same way the app does, so presses start a stroke and releasing the last button
+    /// ends it, and everything that changes the canvas in one go is undone in one step, just like a stroke
+    pub fn apply(self, model: &mut Model) {
+        match self {
+            InputEvent::Move { x, y } => model.mouse_xy = Vector2::new(x, y),
+            InputEvent::Press(button) => {
+                model.begin_stroke();
+                *held(model, button) = true;
+            }
+            InputEvent::Release(button) => {
+                *held(model, button) = false;
+                if !model.left_click_is_held_down && !model.right_click_is_held_down {
+                    model.end_stroke();
+                }
+            }
+            InputEvent::Fill { x, y, value } => {
+                as_stroke(model, |model| model.flood_fill(x, y, value))
+            }
+            InputEvent::Inject { x, y, value } => {
+                as_stroke(model, |model| model.inject(x, y, value))
+            }
+            InputEvent::Clear => as_stroke(model, Model::clear),
+            InputEvent::Invert => as_stroke(model, Model::invert),
+            InputEvent::Undo => {
+                model.undo();
+            }
+            InputEvent::Redo => {
+                model.redo();
+            }
+            InputEvent::Step => model.step_once = true,
+            InputEvent::BrushRadius(radius) => model.brush_radius = radius,
+            InputEvent::DecayFactor(decay_factor) => model.config.decay_factor = decay_factor,
+            InputEvent::PaintValue(value) => model.paint_value = value,
+            InputEvent::Freezing(freezing) => model.freezing = freezing,
+            InputEvent::Paused(paused) => model.paused = paused,
+            InputEvent::ColorMode(color_mode) => model.color_mode = color_mode,
+            InputEvent::PaintColor(color) => model.paint_color = color,
+            InputEvent::DiffusionKernel(kernel) => model.diffusion_kernel = kernel,
+            InputEvent::DiffusionMode(mode) => model.diffusion_mode = mode,
+            InputEvent::DecayMode(mode) => model.decay_mode = mode,
+            InputEvent::Neighbourhood(neighbourhood) => model.neighbourhood = neighbourhood,
+            InputEvent::Symmetry(symmetry) => model.symmetry = symmetry,
+            InputEvent::NoiseRate(rate) => model.noise_rate = rate,
+            InputEvent::AccumulatePaint(accumulate) => model.accumulate_paint = accumulate,
+            InputEvent::EraseHard(erase_hard) => model.erase_hard = erase_hard,
+        }
+    }
+
+    /// Read an event written by `Display`, without its tick
+    fn parse(fields: &[&str]) -> Option<Self> {
+        let button = |name: &str| match name {
+            "l" => Some(MouseButton::Left),
+            "r" => Some(MouseButton::Right),
+            _ => None,
+        };
+        let flag = |value: &str| match value {
+            "1" => Some(true),
+            "0" => Some(false),
+            _ => None,
+        };
+        let cell = |x: &str, y: &str, value: &str| -> Option<(usize, usize, f32)> {
+            Some((x.parse().ok()?, y.parse().ok()?, value.parse().ok()?))
+        };
+
+        match fields {
+            ["m", x, y] => Some(InputEvent::Move {
+                x: x.parse().ok()?,
+                y: y.parse().ok()?,
+            }),
+            ["d", name] => button(name).map(InputEvent::Press),
+            ["u", name] => button(name).map(InputEvent::Release),
+            ["f", x, y, value] => {
+                cell(x, y, value).map(|(x, y, value)| InputEvent::Fill { x, y, value })
+            }
+            ["inject", x, y, value] => {
+                cell(x, y, value).map(|(x, y, value)| InputEvent::Inject { x, y, value })
+            }
+            ["clear"] => Some(InputEvent::Clear),
+            ["invert"] => Some(InputEvent::Invert),
+            ["undo"] => Some(InputEvent::Undo),
+            ["redo"] => Some(InputEvent::Redo),
+            ["step"] => Some(InputEvent::Step),
+            ["r", radius] => radius.parse().ok().map(InputEvent::BrushRadius),
+            ["k", decay_factor] => decay_factor.parse().ok().map(InputEvent::DecayFactor),
+            ["v", value] => value.parse().ok().map(InputEvent::PaintValue),
+            ["i", freezing] => flag(freezing).map(InputEvent::Freezing),
+            ["pause", paused] => flag(paused).map(InputEvent::Paused),
+            ["color-mode", name] => named(&COLOR_MODES, name).map(InputEvent::ColorMode),
+            ["paint-color", r, g, b] => Some(InputEvent::PaintColor([
+                r.parse().ok()?,
+                g.parse().ok()?,
+                b.parse().ok()?,
+            ])),
+            ["kernel", name] => named(&DIFFUSION_KERNELS, name).map(InputEvent::DiffusionKernel),
+            ["diffusion-mode", name] => {
+                named(&DIFFUSION_MODES, name).map(InputEvent::DiffusionMode)
+            }
+            ["decay-mode", name] => named(&DECAY_MODES, name).map(InputEvent::DecayMode),
+            ["neighbourhood", name] => named(&NEIGHBOURHOODS, name).map(InputEvent::Neighbourhood),
+            ["symmetry", "radial", copies] => copies
+                .parse()
+                .ok()
+                .map(Symmetry::RadialN)
+                .map(InputEvent::Symmetry),
+            ["symmetry", name] => named(&SYMMETRIES, name).map(InputEvent::Symmetry),
+            ["noise", rate] => rate.parse().ok().map(InputEvent::NoiseRate),
+            ["accumulate", accumulate] => flag(accumulate).map(InputEvent::AccumulatePaint),
+            ["erase-hard", erase_hard] => flag(erase_hard).map(InputEvent::EraseHard),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for InputEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let button = |button: &MouseButton| match button {
+            MouseButton::Left => "l",
+            MouseButton::Right => "r",
+        };
+
+        match self {
+            InputEvent::Move { x, y } => write!(f, "m {} {}", x, y),
+            InputEvent::Press(pressed) => write!(f, "d {}", button(pressed)),
+            InputEvent::Release(released) => write!(f, "u {}", button(released)),
+            InputEvent::Fill { x, y, value } => write!(f, "f {} {} {}", x, y, value),
+            InputEvent::Inject { x, y, value } => write!(f, "inject {} {} {}", x, y, value),
+            InputEvent::Clear => write!(f, "clear"),
+            InputEvent::Invert => write!(f, "invert"),
+            InputEvent::Undo => write!(f, "undo"),
+            InputEvent::Redo => write!(f, "redo"),
+            InputEvent::Step => write!(f, "step"),
+            InputEvent::BrushRadius(radius) => write!(f, "r {}", radius),
+            InputEvent::DecayFactor(decay_factor) => write!(f, "k {}", decay_factor),
+            InputEvent::PaintValue(value) => write!(f, "v {}", value),
+            InputEvent::Freezing(freezing) => write!(f, "i {}", *freezing as u8),
+            InputEvent::Paused(paused) => write!(f, "pause {}", *paused as u8),
+            InputEvent::ColorMode(mode) => write!(f, "color-mode {}", name_of(&COLOR_MODES, mode)),
+            InputEvent::PaintColor([r, g, b]) => write!(f, "paint-color {} {} {}", r, g, b),
+            InputEvent::DiffusionKernel(kernel) => {
+                write!(f, "kernel {}", name_of(&DIFFUSION_KERNELS, kernel))
+            }
+            InputEvent::DiffusionMode(mode) => {
+                write!(f, "diffusion-mode {}", name_of(&DIFFUSION_MODES, mode))
+            }
+            InputEvent::DecayMode(mode) => write!(f, "decay-mode {}", name_of(&DECAY_MODES, mode)),
+            InputEvent::Neighbourhood(neighbourhood) => {
+                write!(
+                    f,
+                    "neighbourhood {}",
+                    name_of(&NEIGHBOURHOODS, neighbourhood)
+                )
+            }
+            InputEvent::Symmetry(Symmetry::RadialN(copies)) => {
+                write!(f, "symmetry radial {}", copies)
+            }
+            InputEvent::Symmetry(symmetry) => {
+                write!(f, "symmetry {}", name_of(&SYMMETRIES, symmetry))
+            }
+            InputEvent::NoiseRate(rate) => write!(f, "noise {}", rate),
+            InputEvent::AccumulatePaint(accumulate) => {
+                write!(f, "accumulate {}", *accumulate as u8)
+            }
+            InputEvent::EraseHard(erase_hard) => write!(f, "erase-hard {}", *erase_hard as u8),
+        }
+    }
+}
+
+// What the settings are called in recordings
+const COLOR_MODES: [(&str, ColorMode); 2] =
+    [("grayscale", ColorMode::Grayscale), ("rgb", ColorMode::Rgb)];
+const DIFFUSION_KERNELS: [(&str, DiffusionKernel); 2] = [
+    ("uniform", DiffusionKernel::Uniform),
+    ("gaussian", DiffusionKernel::Gaussian),
+];
+const DIFFUSION_MODES: [(&str, DiffusionMode); 2] = [
+    ("diffuse", DiffusionMode::Diffuse),
+    ("anti-diffuse", DiffusionMode::AntiDiffuse),
+];
+const DECAY_MODES: [(&str, DecayMode); 2] = [
+    ("linear", DecayMode::Linear),
+    ("exponential", DecayMode::Exponential),
+];
+const NEIGHBOURHOODS: [(&str, NeighbourhoodKind); 2] = [
+    ("von-neumann", NeighbourhoodKind::VonNeumann),
+    ("moore", NeighbourhoodKind::Moore),
+];
+// `RadialN` is written with its number of copies, so it isn't listed
+const SYMMETRIES: [(&str, Symmetry); 4] = [
+    ("none", Symmetry::None),
+    ("horizontal", Symmetry::Horizontal),
+    ("vertical", Symmetry::Vertical),
+    ("quad", Symmetry::Quad),
+];
+
+/// The name of `value` in `names`
+fn name_of<T: PartialEq>(names: &[(&'static str, T)], value: &T) -> &'static str {
+    names
+        .iter()
+        .find(|(_, named)| named == value)
+        .map(|(name, _)| *name)
+        .expect("every setting has a name")
+}
+
+/// The value called `name` in `names`
+fn named<T: Copy>(names: &[(&str, T)], name: &str) -> Option<T> {
+    names
+        .iter()
+        .find(|(named, _)| *named == name)
+        .map(|(_, value)| *value)
+}
+
+fn held(model: &mut Model, button: MouseButton) -> &mut bool {
+    match button {
+        MouseButton::Left => &mut model.left_click_is_held_down,
+        MouseButton::Right => &mut model.right_click_is_held_down,
+    }
+}
+
+/// Change the canvas with `change` so it's undone in one step, just like a stroke
+fn as_stroke(model: &mut Model, change: impl FnOnce(&mut Model)) {
+    model.begin_stroke();
+    change(model);
+    model.end_stroke();
+}
+
+/// The settings the keys change, as the events that set them to what they are in `model`
+fn settings(model: &Model) -> [InputEvent; 15] {
+    [
+        InputEvent::BrushRadius(model.brush_radius),
+        InputEvent::DecayFactor(model.config.decay_factor),
+        InputEvent::PaintValue(model.paint_value),
+        InputEvent::Freezing(model.freezing),
+        InputEvent::Paused(model.paused),
+        InputEvent::ColorMode(model.color_mode),
+        InputEvent::PaintColor(model.paint_color),
+        InputEvent::DiffusionKernel(model.diffusion_kernel),
+        InputEvent::DiffusionMode(model.diffusion_mode),
+        InputEvent::DecayMode(model.decay_mode),
+        InputEvent::Neighbourhood(model.neighbourhood),
+        InputEvent::Symmetry(model.symmetry),
+        InputEvent::NoiseRate(model.noise_rate),
+        InputEvent::AccumulatePaint(model.accumulate_paint),
+        InputEvent::EraseHard(model.erase_hard),
+    ]
+}
+
+/// Watches the model's mouse and settings from tick to tick and records whatever changed, along with the changes to
+/// the canvas it's told about
+#[derive(Debug)]
+pub struct InputRecorder {
+    updates_per_second: u32,
+    events: Vec<(usize, InputEvent)>,
+    mouse_xy: Option<(f32, f32)>,
+    left: bool,
+    right: bool,
+    settings: Option<[InputEvent; 15]>,
+}
+
+impl InputRecorder {
+    /// A recorder for a model that's updated `updates_per_second` times a second
+    pub fn new(updates_per_second: u32) -> Self {
+        Self {
+            updates_per_second,
+            events: Vec::new(),
+            mouse_xy: None,
+            left: false,
+            right: false,
+            settings: None,
+        }
+    }
+
+    /// Record how the mouse and settings changed since the last tick. Call it right before `Model::update` on each
+    /// tick.
+    pub fn observe(&mut self, tick: usize, model: &Model) {
+        let Vector2 { x, y } = model.mouse_xy;
+        if self.mouse_xy != Some((x, y)) {
+            self.mouse_xy = Some((x, y));
+            self.events.push((tick, InputEvent::Move { x, y }));
+        }
+
+        for &(button, is_held) in &[
+            (MouseButton::Left, model.left_click_is_held_down),
+            (MouseButton::Right, model.right_click_is_held_down),
+        ] {
+            let was_held = match button {
+                MouseButton::Left => &mut self.left,
+                MouseButton::Right => &mut self.right,
+            };
+            if *was_held != is_held {
+                *was_held = is_held;
+                let event = if is_held {
+                    InputEvent::Press(button)
+                } else {
+                    InputEvent::Release(button)
+                };
+                self.events.push((tick, event));
+            }
+        }
+
+        self.observe_settings(tick, model);
+    }
+
+    /// Record an event that doesn't show up in the model's state, like a fill or an undo, as happening before the
+    /// update on `tick`. Settings that changed before it are recorded first, so it's played back with them.
+    pub fn record(&mut self, tick: usize, model: &Model, event: InputEvent) {
+        self.observe_settings(tick, model);
+        self.events.push((tick, event));
+    }
+
+    fn observe_settings(&mut self, tick: usize, model: &Model) {
+        let settings = settings(model);
+        let previous = self.settings.replace(settings);
+        for (index, setting) in settings.iter().enumerate() {
+            if previous.is_none_or(|previous| previous[index] != *setting) {
+                self.events.push((tick, *setting));
+            }
+        }
+    }
+
+    pub fn events(&self) -> &[(usize, InputEvent)] {
+        &self.events
+    }
+
+    /// Write the recording out as text, one event per line after the line with the number of updates a second
+    pub fn to_text(&self) -> String {
+        let events = self
+            .events
+            .iter()
+            .map(|(tick, event)| format!("{} {}\n", tick, event));
+
+        std::iter::once(format!("updates-per-second {}\n", self.updates_per_second))
+            .chain(events)
+            .collect()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), app::Error> {
+        std::fs::write(path, self.to_text())?;
+
+        Ok(())
+    }
+}
+
+/// A recording being played back into a model a tick at a time
+#[derive(Debug)]
+pub struct Replay {
+    updates_per_second: u32,
+    events: Vec<(usize, InputEvent)>,
+    next: usize,
+}
+
+impl Replay {
+    /// Read a recording written by `InputRecorder::to_text`. Blank lines are skipped, anything else that isn't an
+    /// event or the number of updates a second is an error.
+    pub fn parse(text: &str) -> Result<Self, app::Error> {
+        let mut updates_per_second = DEFAULT_UPDATES_PER_SECOND;
+        let mut events = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.is_empty() {
+                continue;
+            }
+
+            if let ["updates-per-second", rate] = fields[..] {
+                updates_per_second =
+                    rate.parse().ok().filter(|rate| *rate > 0).ok_or_else(|| {
+                        app::Error::InvalidReplay {
+                            line: index + 1,
+                            text: line.to_owned(),
+                        }
+                    })?;
+                continue;
+            }
+
+            let event = fields[0]
+                .parse()
+                .ok()
+                .zip(InputEvent::parse(&fields[1..]))
+                .ok_or_else(|| app::Error::InvalidReplay {
+                    line: index + 1,
+                    text: line.to_owned(),
+                })?;
+            events.push(event);
+        }
+
+        // Events are played back in order, so a recording that's been edited by hand still works
+        events.sort_by_key(|(tick, _)| *tick);
+
+        Ok(Self {
+            updates_per_second,
+            events,
+            next: 0,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, app::Error> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Feed `model` every event recorded up to and including `tick` that it hasn't had yet. Call it right before
+    /// `Model::update` on each tick.
+    pub fn apply(&mut self, tick: usize, model: &mut Model) {
+        while let Some((event_tick, event)) = self.events.get(self.next) {
+            if *event_tick > tick {
+                break;
+            }
+
+            event.apply(model);
+            self.next += 1;
+        }
+    }
+
+    /// How many times a second the model was updated while this was recorded
+    pub fn updates_per_second(&self) -> u32 {
+        self.updates_per_second
+    }
+
+    /// Make sure the model is updated as often as it was while this was recorded, since otherwise every tick would
+    /// decay and spread ink by a different amount
+    pub fn check_updates_per_second(&self, updates_per_second: u32) -> Result<(), app::Error> {
+        if updates_per_second == self.updates_per_second {
+            Ok(())
+        } else {
+            Err(app::Error::ReplayRateMismatch {
+                recorded: self.updates_per_second,
+                given: updates_per_second,
+            })
+        }
+    }
+
+    /// How many ticks it takes to play back every event
+    pub fn ticks(&self) -> usize {
+        self.events.last().map_or(0, |(tick, _)| tick + 1)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.events.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cli::Args;
+    use crate::config::SimulationConfig;
+    use crate::rect::Rect;
+
+    fn model() -> Model {
+        Model::new(&SimulationConfig::with_resolution(Rect::new(12, 16)))
+    }
+
+    /// Do what the app does when a key changes the canvas
+    fn perform(model: &mut Model, recorder: &mut InputRecorder, tick: usize, event: InputEvent) {
+        event.apply(model);
+        recorder.record(tick, model, event);
+    }
+
+    #[test]
+    fn test_replay_reproduces_a_session() {
+        let mut recorded = model();
+        let mut recorder = InputRecorder::new(DEFAULT_UPDATES_PER_SECOND);
+        for tick in 0..40 {
+            match tick {
+                2 => recorded.mouse_xy = Vector2::new(3.0, 4.0),
+                3 => recorded.left_click_is_held_down = true,
+                4..=8 => recorded.mouse_xy = Vector2::new(3.0 + tick as f32, 4.5),
+                9 => recorded.left_click_is_held_down = false,
+                14 => {
+                    recorded.mouse_xy = Vector2::new(6.25, 4.0);
+                    recorded.right_click_is_held_down = true;
+                }
+                16 => recorded.right_click_is_held_down = false,
+                18 => {
+                    recorded.brush_radius = 3;
+                    recorded.paint_value = 120.0;
+                    recorded.freezing = true;
+                    recorded.mouse_xy = Vector2::new(10.0, 8.0);
+                    recorded.left_click_is_held_down = true;
+                }
+                19 => {
+                    recorded.left_click_is_held_down = false;
+                    recorded.freezing = false;
+                    recorded.config.decay_factor = 0.5;
+                    let fill = InputEvent::Fill {
+                        x: 0,
+                        y: 0,
+                        value: 40.0,
+                    };
+                    perform(&mut recorded, &mut recorder, tick, fill);
+                }
+                21 => recorded.paused = true,
+                23 => perform(&mut recorded, &mut recorder, tick, InputEvent::Step),
+                25 => {
+                    recorded.paused = false;
+                    recorded.diffusion_kernel = DiffusionKernel::Gaussian;
+                    perform(&mut recorded, &mut recorder, tick, InputEvent::Clear);
+                }
+                26 => perform(&mut recorded, &mut recorder, tick, InputEvent::Undo),
+                28 => {
+                    recorded.color_mode = ColorMode::Rgb;
+                    recorded.cycle_paint_color();
+                    recorded.mouse_xy = Vector2::new(5.0, 5.0);
+                    recorded.left_click_is_held_down = true;
+                }
+                30 => {
+                    recorded.left_click_is_held_down = false;
+                    recorded.color_mode = ColorMode::Grayscale;
+                    let inject = InputEvent::Inject {
+                        x: 2,
+                        y: 9,
+                        value: 75.0,
+                    };
+                    perform(&mut recorded, &mut recorder, tick, inject);
+                    perform(&mut recorded, &mut recorder, tick, InputEvent::Invert);
+                }
+                _ => (),
+            }
+
+            recorder.observe(tick, &recorded);
+            recorded.update(Args::default().step());
+        }
+
+        let mut replay = Replay::parse(&recorder.to_text()).unwrap();
+        assert_eq!(31, replay.ticks());
+
+        let mut replayed = model();
+        for tick in 0..40 {
+            replay.apply(tick, &mut replayed);
+            replayed.update(Args::default().step());
+        }
+
+        assert!(replay.is_finished());
+        assert!(recorded.base_matrix.count_nonzero() > 0);
+        assert_eq!(recorded.base_matrix, replayed.base_matrix);
+        assert!((0..3).any(|channel| recorded.rgb_matrix.channel(channel).count_nonzero() > 0));
+        for channel in 0..3 {
+            assert_eq!(
+                recorded.rgb_matrix.channel(channel),
+                replayed.rgb_matrix.channel(channel)
+            );
+        }
+        assert_eq!(recorded.frozen, replayed.frozen);
+        assert_eq!(recorded.brush_radius, replayed.brush_radius);
+        assert_eq!(recorded.diffusion_kernel, replayed.diffusion_kernel);
+    }
+
+    #[test]
+    fn test_events_round_trip_through_text() {
+        let events = [
+            InputEvent::Move { x: 0.1, y: -3.0 },
+            InputEvent::Press(MouseButton::Right),
+            InputEvent::Release(MouseButton::Left),
+            InputEvent::Fill {
+                x: 4,
+                y: 7,
+                value: 12.5,
+            },
+            InputEvent::BrushRadius(3),
+            InputEvent::DecayFactor(0.25),
+            InputEvent::PaintValue(250.0),
+            InputEvent::Freezing(true),
+            InputEvent::Freezing(false),
+            InputEvent::Inject {
+                x: 1,
+                y: 2,
+                value: 3.0,
+            },
+            InputEvent::Clear,
+            InputEvent::Invert,
+            InputEvent::Undo,
+            InputEvent::Redo,
+            InputEvent::Step,
+            InputEvent::Paused(true),
+            InputEvent::ColorMode(ColorMode::Rgb),
+            InputEvent::PaintColor([1.0, 0.5, 0.0]),
+            InputEvent::DiffusionKernel(DiffusionKernel::Gaussian),
+            InputEvent::DiffusionMode(DiffusionMode::AntiDiffuse),
+            InputEvent::DecayMode(DecayMode::Exponential),
+            InputEvent::Neighbourhood(NeighbourhoodKind::VonNeumann),
+            InputEvent::Symmetry(Symmetry::Quad),
+            InputEvent::Symmetry(Symmetry::RadialN(6)),
+            InputEvent::NoiseRate(0.01),
+            InputEvent::AccumulatePaint(true),
+            InputEvent::EraseHard(false),
+        ];
+        for event in events.iter() {
+            let text = event.to_string();
+            let fields: Vec<&str> = text.split_whitespace().collect();
+            assert_eq!(Some(*event), InputEvent::parse(&fields));
+        }
+    }
+
+    #[test]
+    fn test_replay_is_made_at_the_recorded_updates_per_second() {
+        let recorder = InputRecorder::new(30);
+        let replay = Replay::parse(&recorder.to_text()).unwrap();
+        assert_eq!(30, replay.updates_per_second());
+        assert!(replay.check_updates_per_second(30).is_ok());
+        assert!(matches!(
+            replay.check_updates_per_second(60),
+            Err(app::Error::ReplayRateMismatch {
+                recorded: 30,
+                given: 60
+            })
+        ));
+
+        // Recordings from before the rate was written down were made at the default rate
+        let replay = Replay::parse("0 m 1 2\n").unwrap();
+        assert_eq!(DEFAULT_UPDATES_PER_SECOND, replay.updates_per_second());
+        assert!(Replay::parse("updates-per-second 0\n").is_err());
+    }
+
+    #[test]
+    fn test_invalid_replay() {
+        assert!(matches!(
+            Replay::parse("0 m 1 2\n\n3 d middle\n"),
+            Err(app::Error::InvalidReplay { line: 3, .. })
+        ));
+        assert!(Replay::parse("soon d l").is_err());
+    }
+}
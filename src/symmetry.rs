@@ -0,0 +1,120 @@
+use crate::rect::Rect;
+use std::f32::consts::TAU;
+
+/// How many copies of a stroke `RadialN` makes when cycled to with `next`
+const DEFAULT_RADIAL_COPIES: u32 = 6;
+
+/// Mirror strokes around the center of the canvas
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Symmetry {
+    None,
+    /// Mirror left to right, across the vertical centerline
+    Horizontal,
+    /// Mirror top to bottom, across the horizontal centerline
+    Vertical,
+    /// Mirror across both centerlines
+    Quad,
+    /// Rotate around the center, making this many evenly spaced copies
+    RadialN(u32),
+}
+
+impl Symmetry {
+    /// The symmetry that comes after this one, wrapping back around to `None`
+    pub fn next(self) -> Self {
+        use Symmetry::*;
+        match self {
+            None => Horizontal,
+            Horizontal => Vertical,
+            Vertical => Quad,
+            Quad => RadialN(DEFAULT_RADIAL_COPIES),
+            RadialN(_) => None,
+        }
+    }
+
+    /// Every cell that painting `{x, y}` should also paint, including `{x, y}` itself. Points are clamped to the
+    /// canvas and each one is only listed once, so cells on an axis of symmetry don't get painted twice.
+    pub fn points(self, x: usize, y: usize, canvas: &Rect<usize>) -> Vec<(usize, usize)> {
+        if canvas.w() == 0 || canvas.h() == 0 {
+            return Vec::new();
+        }
+
+        let (max_x, max_y) = (canvas.w() - 1, canvas.h() - 1);
+        let (x, y) = (x.min(max_x), y.min(max_y));
+        let (mirrored_x, mirrored_y) = (max_x - x, max_y - y);
+
+        let mut points = match self {
+            Symmetry::None => vec![(x, y)],
+            Symmetry::Horizontal => vec![(x, y), (mirrored_x, y)],
+            Symmetry::Vertical => vec![(x, y), (x, mirrored_y)],
+            Symmetry::Quad => vec![
+                (x, y),
+                (mirrored_x, y),
+                (x, mirrored_y),
+                (mirrored_x, mirrored_y),
+            ],
+            Symmetry::RadialN(copies) => {
+                let (center_x, center_y) = (max_x as f32 / 2.0, max_y as f32 / 2.0);
+                let (dx, dy) = (x as f32 - center_x, y as f32 - center_y);
+                let copies = copies.max(1);
+
+                (0..copies)
+                    .map(|copy| {
+                        let (sin, cos) = (TAU * copy as f32 / copies as f32).sin_cos();
+                        let rotated_x = (center_x + dx * cos - dy * sin).round();
+                        let rotated_y = (center_y + dx * sin + dy * cos).round();
+
+                        (
+                            rotated_x.clamp(0.0, max_x as f32) as usize,
+                            rotated_y.clamp(0.0, max_y as f32) as usize,
+                        )
+                    })
+                    .collect()
+            }
+        };
+
+        points.sort_unstable();
+        points.dedup();
+        points
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_horizontal_mirrors_across_vertical_centerline() {
+        let points = Symmetry::Horizontal.points(1, 2, &Rect::new(4, 5));
+        assert_eq!(vec![(1, 2), (3, 2)], points);
+    }
+
+    #[test]
+    fn test_center_cell_is_only_listed_once() {
+        let points = Symmetry::Quad.points(2, 2, &Rect::new(5, 5));
+        assert_eq!(vec![(2, 2)], points);
+    }
+
+    #[test]
+    fn test_radial_makes_evenly_spaced_copies() {
+        let points = Symmetry::RadialN(4).points(4, 0, &Rect::new(5, 5));
+        assert_eq!(vec![(0, 0), (0, 4), (4, 0), (4, 4)], points);
+    }
+
+    #[test]
+    fn test_radial_points_are_clamped_to_canvas() {
+        let canvas = Rect::new(3, 9);
+        for (x, y) in Symmetry::RadialN(7).points(0, 0, &canvas) {
+            assert!(canvas.contains(x, y));
+        }
+    }
+
+    #[test]
+    fn test_next_cycles_back_to_none() {
+        let mut symmetry = Symmetry::None;
+        for _ in 0..5 {
+            symmetry = symmetry.next();
+        }
+
+        assert_eq!(Symmetry::None, symmetry);
+    }
+}
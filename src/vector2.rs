@@ -1,3 +1,5 @@
+use std::ops::{Add, Mul, Sub};
+
 pub struct Vector2<NumericType> {
     pub x: NumericType,
     pub y: NumericType,
@@ -14,13 +16,98 @@ where
     NumericType: Clone + Copy,
 {
     fn clone(&self) -> Self {
-        Vector2 {
-            x: self.x,
-            y: self.y,
-        }
+        *self
     }
 }
 
-impl<NumericType> Copy for Vector2<NumericType>
+impl<NumericType> Copy for Vector2<NumericType> where NumericType: Copy {}
+
+impl<NumericType> Add for Vector2<NumericType>
 where
-    NumericType: Copy {}
+    NumericType: Add<Output = NumericType>,
+{
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Vector2::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl<NumericType> Sub for Vector2<NumericType>
+where
+    NumericType: Sub<Output = NumericType>,
+{
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Vector2::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl Mul<f32> for Vector2<f32> {
+    type Output = Self;
+
+    fn mul(self, scale: f32) -> Self {
+        Vector2::new(self.x * scale, self.y * scale)
+    }
+}
+
+impl Vector2<f32> {
+    pub fn length(&self) -> f32 {
+        self.x.hypot(self.y)
+    }
+
+    pub fn distance(&self, other: &Self) -> f32 {
+        (*other - *self).length()
+    }
+
+    /// The point `t` of the way from `self` to `other`, where `t` of 0.0 is `self` and 1.0 is `other`
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        *self + (*other - *self) * t
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_add() {
+        let sum = Vector2::new(1, 2) + Vector2::new(3, -4);
+        assert_eq!((4, -2), (sum.x, sum.y));
+    }
+
+    #[test]
+    fn test_sub() {
+        let difference = Vector2::new(1.0, 2.0) - Vector2::new(3.0, -4.0);
+        assert_eq!((-2.0, 6.0), (difference.x, difference.y));
+    }
+
+    #[test]
+    fn test_mul() {
+        let scaled = Vector2::new(1.5, -2.0) * 2.0;
+        assert_eq!((3.0, -4.0), (scaled.x, scaled.y));
+    }
+
+    #[test]
+    fn test_length_and_distance() {
+        assert_eq!(5.0, Vector2::new(3.0, 4.0).length());
+        assert_eq!(
+            5.0,
+            Vector2::new(1.0, 1.0).distance(&Vector2::new(4.0, -3.0))
+        );
+    }
+
+    #[test]
+    fn test_lerp() {
+        let from = Vector2::new(2.0, 10.0);
+        let to = Vector2::new(4.0, 0.0);
+
+        let start = from.lerp(&to, 0.0);
+        let middle = from.lerp(&to, 0.5);
+        let end = from.lerp(&to, 1.0);
+        assert_eq!((2.0, 10.0), (start.x, start.y));
+        assert_eq!((3.0, 5.0), (middle.x, middle.y));
+        assert_eq!((4.0, 0.0), (end.x, end.y));
+    }
+}